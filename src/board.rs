@@ -1,80 +1,675 @@
-// Cells are identified as A1 (top-right) to G6 (bottom-right)
-// Isolating the most significant bit is more difficult than the least significant bit, so the
-// bitboard repr uses A1 as lsb 8, and G6 as the msb 48. The remaining 16 bits are unused.
-// The first row is filled with arbitrary pieces to facilitate finding the lsb of the bottom row.
+// Cell index = row * 7 + file, where row 0 is the top row and file 0 the leftmost: bit 0 is the
+// top-left cell and bit 41 the bottom-right. Notation lists rows in the same order, top first.
+// Bits 42-48 are a sentinel row beneath the board, set in both colors, so every stack rests on
+// something and the cell above a stack is one shift (>> 7) away. The remaining 15 bits are unused.
+//
+// Because of gravity, the left-right mirror is the only symmetry a position has. Flipping the
+// board upside down leaves pieces floating, so there is deliberately no vertical mirror.
 
 use anyhow::{anyhow, bail};
 use arrayvec::ArrayVec;
 use colored::Colorize;
-use std::fmt;
+use rand::Rng;
+use std::{fmt, io::BufRead};
 
-use crate::constants::{BOARD_MASK, EMPTY_BOARD, FILE, GAME_MASK, ROW};
+use crate::constants::{
+    BOARD_MASK, CENTER_STACK, DIRECTIONS, Direction, EMPTY_BOARD, EVEN_ROWS, FILE, GAME_MASK,
+    ODD_ROWS, ROW,
+};
+use crate::minimax::Minimax;
 
 #[derive(Debug, Clone, PartialEq, Copy)]
 pub struct Board {
     red: u64,
     yellow: u64,
+    /// Pieces per file, kept in step with the bitboards so the hot insert/remove path can find
+    /// the top of a file without recomputing `all`.
+    heights: [u8; 7],
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BoardError {
+    InvalidFile(usize),
+    EmptyFile(usize),
+    FullFile(usize),
+    InvalidCompact(u64),
+    /// Cells holding both a red and a yellow piece.
+    Overlapping(u64),
+    /// Bits set outside `BOARD_MASK`.
+    OffBoard(u64),
+    /// The sentinel row beneath the board isn't fully set in both bitboards.
+    MissingSentinel,
+    /// Pieces with an empty cell beneath them.
+    Floating(u64),
+    /// Red and yellow piece counts more than one apart.
+    Unbalanced(u32, u32),
+    /// A (row, file) pair off the board.
+    InvalidCell(usize, usize),
+    /// A line of a position file that isn't valid notation, with its 1-based line number.
+    InvalidNotation(usize, String),
+    /// Reading a position file failed.
+    Io(String),
+    /// An ASCII diagram that isn't six rows of seven cells.
+    InvalidAscii(String),
+}
+
+/// The terms of `Board::evaluate`. Per-direction counts are ordered horizontal, vertical,
+/// diagonal, antidiagonal.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct EvalBreakdown {
+    pub red_potential: [u32; 4],
+    pub yellow_potential: [u32; 4],
+    pub red_connect_4: [u32; 4],
+    pub yellow_connect_4: [u32; 4],
+    pub red_open_threes: u32,
+    pub yellow_open_threes: u32,
+    pub parity: i32,
+    /// Red's pieces on the central stack minus Yellow's.
+    pub center: i32,
+    pub score: i32,
+}
+
+impl EvalBreakdown {
+    /// The score these terms add up to under `weights`.
+    pub fn weighted(&self, weights: &EvalWeights) -> i32 {
+        let total = |counts: [u32; 4]| counts.iter().sum::<u32>() as i32;
+
+        (total(self.red_potential) - total(self.yellow_potential)) * weights.potential
+            + (total(self.red_connect_4) - total(self.yellow_connect_4)) * weights.connect_4
+            + (self.red_open_threes as i32 - self.yellow_open_threes as i32) * weights.open_three
+            + self.parity * weights.parity
+            + self.center * weights.center
+    }
+}
+
+/// Multipliers for each term of the evaluation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EvalWeights {
+    pub potential: i32,
+    pub connect_4: i32,
+    pub open_three: i32,
+    pub parity: i32,
+    /// Bonus per piece on the central stack, the cells with the most lines through them.
+    pub center: i32,
+    /// If set, potential lines only run through empty cells at most this many drops above their
+    /// column's top piece. Late in the game this stops lines through cells that will not be
+    /// reached in time from counting.
+    pub reach: Option<usize>,
+}
+
+impl Default for EvalWeights {
+    fn default() -> Self {
+        EvalWeights {
+            potential: 1,
+            connect_4: 42,
+            open_three: 4,
+            parity: 1,
+            center: 1,
+            reach: None,
+        }
+    }
+}
+
+/// A board paired with the cell a pending piece would land in, kept apart from the bitboards so
+/// front-ends can draw it in a different style.
+#[derive(Debug, Clone, PartialEq, Copy)]
+pub struct PreviewBoard {
+    pub board: Board,
+    pub color: Color,
+    /// Cell index (0-41, top-left first) of the pending piece, or None if the file is full.
+    pub cell: Option<usize>,
+}
+
+/// What happened when a piece was dropped with `Board::try_move`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MoveResult {
+    /// The row the piece landed in, counting up from 0 at the bottom as in `piece_at`.
+    pub row: usize,
+    pub file: usize,
+    /// Whether the piece completed a connect-4.
+    pub won: bool,
+    /// Whether the board is now full, which is a draw unless `won`.
+    pub full: bool,
+}
+
+/// A position's `Board::to_compact` encoding, for keying maps and sets. Unlike the bitboards it
+/// has no sentinel row or spare bits, so each position has exactly one key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PositionKey(u64);
+
+/// How far a game has progressed, by the number of pieces on the board.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// Fewer than 10 pieces.
+    Opening,
+    /// 10 to 27 pieces.
+    Midgame,
+    /// 28 pieces or more.
+    Endgame,
 }
 
 #[repr(u8)]
 #[derive(Debug, Clone, PartialEq, Copy)]
+#[cfg_attr(
+    feature = "save",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
 pub enum Color {
     Red = 0,
     Yellow = 1,
 }
 
+/// Rows counted from the bottom, so the bottom row is odd.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RowParity {
+    Odd,
+    Even,
+}
+
+/// How a color's threats split between odd and even rows, from `Board::parity_summary`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParityInfo {
+    pub odd: u32,
+    pub even: u32,
+    /// The rows whose threats win zugzwang fights: odd for the side that moved first, even for
+    /// the side that moved second.
+    pub favored: RowParity,
+    /// Files holding a threat on the favored rows, in file order.
+    pub key_files: ArrayVec<usize, 7>,
+}
+
 impl Board {
     pub fn new() -> Self {
         Board {
             red: EMPTY_BOARD,
             yellow: EMPTY_BOARD,
+            heights: [0; 7],
         }
     }
 
+    /// Parses six rows of seven cells, top row first, separated by `/`. Each row lists `r` and
+    /// `y` pieces with digits standing for runs of empty cells.
     pub fn from_notation(notation: &str) -> Result<Self, anyhow::Error> {
         // "7/7/7/7/5r1/4yr1/4ryy"
 
+        let rows = notation.split('/').collect::<Vec<_>>();
+        if rows.len() != 6 {
+            bail!(
+                "expected 6 rows in notation, got {}: {}",
+                rows.len(),
+                notation
+            );
+        }
+
         let mut board = Board::new();
-        let mut index = 0;
+        for (row, cells) in rows.into_iter().enumerate() {
+            let mut file = 0;
+            for ch in cells.chars() {
+                match ch {
+                    c if c.is_ascii_digit() => {
+                        file += c
+                            .to_digit(10)
+                            .ok_or(anyhow!("could not parse digit: {}", c))?
+                            as usize
+                    }
+                    'r' if file < 7 => {
+                        board.red |= 1 << (row * 7 + file);
+                        file += 1;
+                    }
+                    'y' if file < 7 => {
+                        board.yellow |= 1 << (row * 7 + file);
+                        file += 1;
+                    }
+                    // Counted so the error below can say how long the row is.
+                    'r' | 'y' => file += 1,
+                    c => bail!("invalid character in notation: {}", c),
+                }
+            }
 
-        for ch in notation.chars() {
-            if index >= 42 {
+            if file != 7 {
                 bail!(
-                    "invalid row count in notation. expected 6, got {}: {}",
-                    notation.split("/").count(),
+                    "row {} of notation has {} cells, too {}; expected 7: {}",
+                    row + 1,
+                    file,
+                    if file > 7 { "many" } else { "few" },
                     notation
                 );
             }
-            match ch {
-                c if c.is_ascii_digit() => {
-                    index += c
-                        .to_digit(10)
-                        .ok_or(anyhow!("could not parse digit: {}", c))?
+        }
+
+        board.sync_heights();
+        Ok(board)
+    }
+
+    /// `from_notation` for positions typed in by hand, rejecting any that `validate` does.
+    pub fn from_notation_strict(notation: &str) -> Result<Self, anyhow::Error> {
+        let board = Board::from_notation(notation)?;
+        board.validate()?;
+        Ok(board)
+    }
+
+    /// Parses a diagram like `render_plain` prints: six rows of `R`, `Y` and `.`, top row first,
+    /// either case. Spaces, `|` and the brackets around highlighted pieces are ignored, as are
+    /// blank lines and column headers of digits or letters A-G. The position must be legal.
+    pub fn from_ascii(art: &str) -> Result<Self, BoardError> {
+        let header = |line: &str| {
+            line.chars().all(|c| {
+                c.is_ascii_digit()
+                    || c.is_whitespace()
+                    || ('A'..='G').contains(&c.to_ascii_uppercase())
+            })
+        };
+
+        let mut board = Board::new();
+        let mut row = 0;
+        for line in art.lines().filter(|line| !header(line)) {
+            if row == 6 {
+                return Err(BoardError::InvalidAscii(format!(
+                    "more than 6 rows: {}",
+                    line
+                )));
+            }
+
+            let mut file = 0;
+            for c in line.chars().filter(|c| !matches!(c, ' ' | '|' | '[' | ']')) {
+                let cell = match file {
+                    0..7 => 1 << (row * 7 + file),
+                    _ => 0,
+                };
+                match c {
+                    'R' | 'r' => board.red |= cell,
+                    'Y' | 'y' => board.yellow |= cell,
+                    '.' => {}
+                    c => return Err(BoardError::InvalidAscii(format!("unexpected '{}'", c))),
+                }
+                file += 1;
+            }
+            if file != 7 {
+                return Err(BoardError::InvalidAscii(format!(
+                    "row {} has {} cells, expected 7: {}",
+                    row + 1,
+                    file,
+                    line.trim()
+                )));
+            }
+            row += 1;
+        }
+        if row != 6 {
+            return Err(BoardError::InvalidAscii(format!(
+                "{} rows, expected 6",
+                row
+            )));
+        }
+
+        board.validate()?;
+        board.sync_heights();
+        Ok(board)
+    }
+
+    /// Lazily parses one notation string per line, skipping blank lines and `#` comments.
+    pub fn parse_many(reader: impl BufRead) -> impl Iterator<Item = Result<Board, BoardError>> {
+        reader.lines().enumerate().filter_map(|(index, line)| {
+            let line = match line {
+                Ok(line) => line,
+                Err(e) => return Some(Err(BoardError::Io(e.to_string()))),
+            };
+            let notation = line.trim();
+            if notation.is_empty() || notation.starts_with('#') {
+                return None;
+            }
+
+            Some(
+                Board::from_notation(notation)
+                    .map_err(|e| BoardError::InvalidNotation(index + 1, e.to_string())),
+            )
+        })
+    }
+
+    /// The inverse of `from_notation`, e.g. "7/7/7/7/3y3/2rr3".
+    pub fn to_notation(&self) -> String {
+        let mut notation = String::new();
+        let mut empty = 0;
+
+        for i in 0..42 {
+            let piece = match (self.red & (1 << i), self.yellow & (1 << i)) {
+                (0, 0) => None,
+                (0, _) => Some('y'),
+                _ => Some('r'),
+            };
+            match piece {
+                Some(c) => {
+                    if empty > 0 {
+                        notation.push_str(&empty.to_string());
+                        empty = 0;
+                    }
+                    notation.push(c);
                 }
-                'r' => {
-                    board.red |= 1 << index;
-                    index += 1;
+                None => empty += 1,
+            }
+            if i % 7 == 6 {
+                if empty > 0 {
+                    notation.push_str(&empty.to_string());
+                    empty = 0;
                 }
-                'y' => {
-                    board.yellow |= 1 << index;
-                    index += 1;
+                if i != 41 {
+                    notation.push('/');
                 }
-                '/' => {
-                    if index % 7 != 0 {
-                        bail!(
-                            "invalid row count in notation ending at index {}: {}",
-                            index,
-                            notation
-                        );
-                    }
+            }
+        }
+
+        notation
+    }
+
+    /// Builds a board from a sequence of files (1-7), played alternately starting with Red.
+    pub fn from_moves(moves: &str) -> Result<Self, anyhow::Error> {
+        let mut board = Board::new();
+        let mut color = Color::Red;
+
+        for ch in moves.chars() {
+            let file = ch
+                .to_digit(10)
+                .filter(|d| (1..=7).contains(d))
+                .ok_or(anyhow!("invalid file in move sequence: {}", ch))?
+                as usize
+                - 1;
+            if !board.legal_files().contains(&file) {
+                bail!("file {} is full in move sequence: {}", file + 1, moves);
+            }
+            board.insert(file, color);
+            color = color.other();
+        }
+
+        Ok(board)
+    }
+
+    /// Packs the position into 49 bits, 7 per file from file 1 upwards. Within a file, each piece
+    /// from the bottom up is 1 for red and 0 for yellow, followed by a sentinel 1 above the stack.
+    pub fn to_compact(&self) -> u64 {
+        let mut code = 0;
+
+        for file in 0..7 {
+            let mut column = 1 << self.height(file);
+            for (height, color) in self.column(file).enumerate() {
+                if color == Color::Red {
+                    column |= 1 << height;
+                }
+            }
+            code |= column << (file * 7);
+        }
+
+        code
+    }
+
+    pub fn from_compact(code: u64) -> Result<Self, BoardError> {
+        if code >> 49 != 0 {
+            return Err(BoardError::InvalidCompact(code));
+        }
+
+        let mut board = Board::new();
+        for file in 0..7 {
+            let column = (code >> (file * 7)) & 0x7F;
+            if column == 0 {
+                return Err(BoardError::InvalidCompact(code));
+            }
+
+            let height = 63 - column.leading_zeros();
+            for h in 0..height {
+                match column & (1 << h) {
+                    0 => board.insert(file, Color::Yellow),
+                    _ => board.insert(file, Color::Red),
                 }
-                c => bail!("invalid character in notation: {}", c),
             }
         }
 
         Ok(board)
     }
 
+    /// The raw red bitboard, in the layout described at the top of this file, sentinel row
+    /// included.
+    pub fn red_bits(&self) -> u64 {
+        self.red
+    }
+
+    /// The raw yellow bitboard, sentinel row included.
+    pub fn yellow_bits(&self) -> u64 {
+        self.yellow
+    }
+
+    /// The inverse of `red_bits` and `yellow_bits`, rejecting anything `validate` does.
+    pub fn from_bits(red: u64, yellow: u64) -> Result<Self, BoardError> {
+        let mut board = Board {
+            red,
+            yellow,
+            heights: [0; 7],
+        };
+        board.validate()?;
+        board.sync_heights();
+        Ok(board)
+    }
+
+    /// The file `file` lands on when the board is reflected left to right.
+    pub fn mirror_file(file: usize) -> usize {
+        FILE.len() - 1 - file
+    }
+
+    /// Reflects the board left to right, so file f becomes file 6 - f. A position and its mirror
+    /// are equally legal and have the same value.
+    pub fn mirror_horizontal(&self) -> Board {
+        let reflect = |bits: u64| {
+            (0..7).fold(0, |mirrored, file| {
+                let column = bits & FILE[file];
+                mirrored
+                    | match file {
+                        0..3 => column << (6 - 2 * file),
+                        _ => column >> (2 * file - 6),
+                    }
+            })
+        };
+
+        let mut heights = self.heights;
+        heights.reverse();
+
+        Board {
+            red: reflect(self.red),
+            yellow: reflect(self.yellow),
+            heights,
+        }
+    }
+
+    /// Whether `other` is this position reflected left to right.
+    pub fn is_mirror_of(&self, other: &Board) -> bool {
+        self.mirror_horizontal() == *other
+    }
+
+    /// Swaps the colors of every piece.
+    pub fn flip_colors(&self) -> Board {
+        Board {
+            red: self.yellow,
+            yellow: self.red,
+            heights: self.heights,
+        }
+    }
+
+    /// A canonical form for deduping training data, identical for a position and its mirror
+    /// image, and for a position and its color-flipped counterpart with the other side to move.
+    /// The returned color is the side to move in the normalized board, which is always Red.
+    pub fn normalize(&self, to_move: Color) -> (Board, Color) {
+        let board = match to_move {
+            Color::Red => *self,
+            Color::Yellow => self.flip_colors(),
+        };
+        let mirrored = board.mirror_horizontal();
+
+        match board.to_compact() <= mirrored.to_compact() {
+            true => (board, Color::Red),
+            false => (mirrored, Color::Red),
+        }
+    }
+
+    /// Whether `validate` accepts the board.
+    pub fn is_legal(&self) -> bool {
+        self.validate().is_ok()
+    }
+
+    /// Checks that the bitboards describe a real position: no cell holds both colors, nothing is
+    /// set off the board, the sentinel row is intact, every piece rests on the one below it and
+    /// neither side has moved more than once in a row.
+    pub fn validate(&self) -> Result<(), BoardError> {
+        let overlapping = self.red & self.yellow & GAME_MASK;
+        if overlapping != 0 {
+            return Err(BoardError::Overlapping(overlapping));
+        }
+        let off_board = self.all() & !BOARD_MASK;
+        if off_board != 0 {
+            return Err(BoardError::OffBoard(off_board));
+        }
+        if self.red & EMPTY_BOARD != EMPTY_BOARD || self.yellow & EMPTY_BOARD != EMPTY_BOARD {
+            return Err(BoardError::MissingSentinel);
+        }
+
+        // A piece is supported when the cell beneath it, or the sentinel row, is occupied.
+        let floating = self.all() & GAME_MASK & !(self.all() >> 7);
+        if floating != 0 {
+            return Err(BoardError::Floating(floating));
+        }
+
+        let red = (self.red & GAME_MASK).count_ones();
+        let yellow = (self.yellow & GAME_MASK).count_ones();
+        if red.abs_diff(yellow) > 1 {
+            return Err(BoardError::Unbalanced(red, yellow));
+        }
+
+        Ok(())
+    }
+
+    /// Sets a cell to `color` without regard for gravity, replacing any piece already there. For
+    /// building puzzles and tests only; check the result with `is_legal` before playing from it.
+    /// `row` counts from the bottom, like `height`.
+    pub fn place(&mut self, row: usize, file: usize, color: Color) -> Result<(), BoardError> {
+        if row >= 6 || file >= 7 {
+            return Err(BoardError::InvalidCell(row, file));
+        }
+
+        let cell = 1 << ((5 - row) * 7 + file);
+        match color {
+            Color::Red => {
+                self.red |= cell;
+                self.yellow &= !cell;
+            }
+            Color::Yellow => {
+                self.yellow |= cell;
+                self.red &= !cell;
+            }
+        }
+        self.sync_heights();
+
+        Ok(())
+    }
+
+    /// The piece at `row` (counting from the bottom) of `file`, if any.
+    pub fn piece_at(&self, row: usize, file: usize) -> Option<Color> {
+        if row >= 6 || file >= 7 {
+            return None;
+        }
+
+        let cell = 1 << ((5 - row) * 7 + file);
+        match (self.red & cell, self.yellow & cell) {
+            (0, 0) => None,
+            (0, _) => Some(Color::Yellow),
+            _ => Some(Color::Red),
+        }
+    }
+
+    /// The pieces in `file` from the bottom up.
+    pub fn column(&self, file: usize) -> impl Iterator<Item = Color> {
+        let board = *self;
+        (0..self.height(file)).map(move |height| {
+            let cell = 1 << ((5 - height) * 7 + file);
+            match board.red & cell {
+                0 => Color::Yellow,
+                _ => Color::Red,
+            }
+        })
+    }
+
+    /// Every piece on the board as (cell index, color), in cell order from the top-left.
+    pub fn pieces(&self) -> impl Iterator<Item = (usize, Color)> {
+        let red = self.red & GAME_MASK;
+        let mut occupied = self.all() & GAME_MASK;
+        std::iter::from_fn(move || {
+            if occupied == 0 {
+                return None;
+            }
+
+            let cell = occupied.trailing_zeros() as usize;
+            occupied &= occupied - 1;
+            let color = match red & (1 << cell) {
+                0 => Color::Yellow,
+                _ => Color::Red,
+            };
+            Some((cell, color))
+        })
+    }
+
+    /// The number of pieces in `file`.
+    #[inline(always)]
+    pub fn height(&self, file: usize) -> usize {
+        self.heights[file] as usize
+    }
+
+    /// The number of pieces in each file, left to right.
+    pub fn heights(&self) -> [u8; 7] {
+        self.heights
+    }
+
+    /// Recounts the height cache from the bitboards after they are edited directly.
+    fn sync_heights(&mut self) {
+        let all = self.all() & GAME_MASK;
+        for (height, file) in self.heights.iter_mut().zip(FILE) {
+            *height = (file & all).count_ones() as u8;
+        }
+    }
+
+    /// The number of pieces on the board.
+    pub fn piece_count(&self) -> usize {
+        (self.all() & GAME_MASK).count_ones() as usize
+    }
+
+    /// The side to move, from the piece counts: whoever is a piece behind, or Red when they are
+    /// level, since the board alone doesn't say who opened then.
+    pub fn side_to_move(&self) -> Color {
+        let red = (self.red & GAME_MASK).count_ones();
+        let yellow = (self.yellow & GAME_MASK).count_ones();
+        match red > yellow {
+            true => Color::Yellow,
+            false => Color::Red,
+        }
+    }
+
+    pub fn phase(&self) -> Phase {
+        match self.piece_count() {
+            0..10 => Phase::Opening,
+            10..28 => Phase::Midgame,
+            _ => Phase::Endgame,
+        }
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.empty() & GAME_MASK == 0
+    }
+
+    /// Fills every empty cell with `color`, ignoring any lines that makes. For building full
+    /// and near-full positions in tests.
+    pub fn fill_remaining(&mut self, color: Color) {
+        let empty = self.empty() & GAME_MASK;
+        match color {
+            Color::Red => self.red |= empty,
+            Color::Yellow => self.yellow |= empty,
+        }
+        self.heights = [6; 7];
+    }
+
     #[inline(always)]
     pub fn all(&self) -> u64 {
         self.red | self.yellow
@@ -85,203 +680,1710 @@ impl Board {
         !(self.red | self.yellow) & BOARD_MASK
     }
 
+    /// The single empty cell above the stack in `file`, or 0 if the file is full.
+    #[inline(always)]
+    fn landing_cell(&self, file: usize) -> u64 {
+        match self.heights[file] {
+            6 => 0,
+            height => 1 << ((5 - height as usize) * 7 + file),
+        }
+    }
+
     pub fn insert(&mut self, file: usize, color: Color) {
-        let file = FILE[file] & self.all();
-        let cell = (file >> 7) & !self.all();
+        let cell = self.landing_cell(file);
 
         self.red |= cell * ((color as u64) ^ 1);
         self.yellow |= cell * (color as u64);
+        self.heights[file] += (cell != 0) as u8;
+    }
+
+    /// Drops a piece like `insert`, rejecting files that are off the board or full, and reports
+    /// where it landed and whether the game is over.
+    pub fn try_move(&mut self, file: usize, color: Color) -> Result<MoveResult, BoardError> {
+        if file >= FILE.len() {
+            return Err(BoardError::InvalidFile(file));
+        }
+        if self.heights[file] == 6 {
+            return Err(BoardError::FullFile(file));
+        }
+
+        let row = self.heights[file] as usize;
+        self.insert(file, color);
+
+        Ok(MoveResult {
+            row,
+            file,
+            won: self.last_move_wins(file),
+            full: self.is_full(),
+        })
     }
 
-    pub fn remove(&mut self, file: usize) {
-        let file = FILE[file] & self.all() & GAME_MASK;
-        let lsb = file & (!file + 1);
-        self.red &= !lsb;
-        self.yellow &= !lsb;
+    pub fn with_preview(&self, file: usize, color: Color) -> PreviewBoard {
+        let cell = self.landing_cell(file);
+        PreviewBoard {
+            board: *self,
+            color,
+            cell: (cell != 0).then(|| cell.trailing_zeros() as usize),
+        }
     }
 
-    pub fn evaluate(&self) -> i32 {
-        let mut score: i32 = 0;
+    /// The file and color of the single piece dropped to turn `self` into `next`, or None if they
+    /// differ by anything other than one legal drop.
+    pub fn infer_move(&self, next: &Board) -> Option<(usize, Color)> {
+        if self.red & !next.red != 0 || self.yellow & !next.yellow != 0 {
+            return None;
+        }
 
-        let red_pieces = self.red & GAME_MASK;
-        let yellow_pieces = self.yellow & GAME_MASK;
-        let empty = self.empty();
-
-        // This starts with all the red pieces, and then performs all subsequent scanning with
-        // empty cells - indicating the "potential" for a connect-4. The result is ANDed with the
-        // original piece positions to count every piece that is potentially part of a connect-4.
-        // Ideally this should not score piece placements that can never acheive a connect-4.
-
-        let mut horizontal_check = red_pieces;
-        let mut vertical_check = red_pieces;
-        let mut diagonal_check = red_pieces;
-        let mut antidiagonal_check = red_pieces;
-        for _ in 0..3 {
-            horizontal_check = ((horizontal_check & !FILE[0]) >> 1) & (red_pieces | empty);
-            vertical_check = ((vertical_check & !ROW[5]) >> 7) & (red_pieces | empty);
-            diagonal_check = ((diagonal_check & !ROW[5] & !FILE[6]) >> 6) & (red_pieces | empty);
-            antidiagonal_check =
-                ((antidiagonal_check & !ROW[5] & !FILE[0]) >> 8) & (red_pieces | empty);
-        }
-
-        score += (horizontal_check & red_pieces).count_ones() as i32;
-        score += (vertical_check & red_pieces).count_ones() as i32;
-        score += (diagonal_check & red_pieces).count_ones() as i32;
-        score += (antidiagonal_check & red_pieces).count_ones() as i32;
-
-        let mut horizontal_check = yellow_pieces;
-        let mut vertical_check = yellow_pieces;
-        let mut diagonal_check = yellow_pieces;
-        let mut antidiagonal_check = yellow_pieces;
-        for _ in 0..3 {
-            horizontal_check = ((horizontal_check & !FILE[0]) >> 1) & (yellow_pieces | empty);
-            vertical_check = ((vertical_check & !ROW[5]) >> 7) & (yellow_pieces | empty);
-            diagonal_check = ((diagonal_check & !ROW[5] & !FILE[6]) >> 6) & (yellow_pieces | empty);
-            antidiagonal_check =
-                ((antidiagonal_check & !ROW[5] & !FILE[0]) >> 8) & (yellow_pieces | empty);
-        }
-
-        score -= (horizontal_check & yellow_pieces).count_ones() as i32;
-        score -= (vertical_check & yellow_pieces).count_ones() as i32;
-        score -= (diagonal_check & yellow_pieces).count_ones() as i32;
-        score -= (antidiagonal_check & yellow_pieces).count_ones() as i32;
+        let (added, color) = match (next.red & !self.red, next.yellow & !self.yellow) {
+            (red, 0) if red.count_ones() == 1 => (red, Color::Red),
+            (0, yellow) if yellow.count_ones() == 1 => (yellow, Color::Yellow),
+            _ => return None,
+        };
 
-        // Separate scoring for actual connect-4
+        let file = added.trailing_zeros() as usize % 7;
+        (self.landing_cell(file) == added).then_some((file, color))
+    }
 
-        let mut horizontal_check = red_pieces;
-        let mut vertical_check = red_pieces;
-        let mut diagonal_check = red_pieces;
-        let mut antidiagonal_check = red_pieces;
-        for _ in 0..3 {
-            horizontal_check = ((horizontal_check & !FILE[0]) >> 1) & red_pieces;
-            vertical_check = ((vertical_check & !ROW[5]) >> 7) & red_pieces;
-            diagonal_check = ((diagonal_check & !ROW[5] & !FILE[6]) >> 6) & red_pieces;
-            antidiagonal_check = ((antidiagonal_check & !ROW[5] & !FILE[0]) >> 8) & red_pieces;
+    /// Removes the top piece of `file`, returning its color.
+    pub fn remove(&mut self, file: usize) -> Result<Color, BoardError> {
+        if file >= FILE.len() {
+            return Err(BoardError::InvalidFile(file));
         }
 
-        score += (horizontal_check & red_pieces).count_ones() as i32 * 42;
-        score += (vertical_check & red_pieces).count_ones() as i32 * 42;
-        score += (diagonal_check & red_pieces).count_ones() as i32 * 42;
-        score += (antidiagonal_check & red_pieces).count_ones() as i32 * 42;
+        let height = match self.heights[file] {
+            0 => return Err(BoardError::EmptyFile(file)),
+            height => height as usize,
+        };
+        let cell = 1 << ((6 - height) * 7 + file);
+
+        let color = match self.red & cell {
+            0 => Color::Yellow,
+            _ => Color::Red,
+        };
+        self.red &= !cell;
+        self.yellow &= !cell;
+        self.heights[file] -= 1;
+
+        Ok(color)
+    }
 
-        let mut horizontal_check = yellow_pieces;
-        let mut vertical_check = yellow_pieces;
-        let mut diagonal_check = yellow_pieces;
-        let mut antidiagonal_check = yellow_pieces;
-        for _ in 0..3 {
-            horizontal_check = ((horizontal_check & !FILE[0]) >> 1) & yellow_pieces;
-            vertical_check = ((vertical_check & !ROW[5]) >> 7) & yellow_pieces;
-            diagonal_check = ((diagonal_check & !ROW[5] & !FILE[6]) >> 6) & yellow_pieces;
-            antidiagonal_check = ((antidiagonal_check & !ROW[5] & !FILE[0]) >> 8) & yellow_pieces;
+    /// Removes the bottom piece of `file` and drops the rest of the column by one, as in the Pop
+    /// Out variant. Returns the removed piece's color.
+    pub fn pop_out(&mut self, file: usize) -> Result<Color, BoardError> {
+        if file >= FILE.len() {
+            return Err(BoardError::InvalidFile(file));
         }
+        if self.heights[file] == 0 {
+            return Err(BoardError::EmptyFile(file));
+        }
+
+        let column = FILE[file] & GAME_MASK;
+        let bottom = column & ROW[0];
+        let color = match self.red & bottom {
+            0 => Color::Yellow,
+            _ => Color::Red,
+        };
+        let drop = |bits: u64| (bits & !column) | ((bits & column & !bottom) << 7);
+        self.red = drop(self.red);
+        self.yellow = drop(self.yellow);
+        self.heights[file] -= 1;
+
+        Ok(color)
+    }
 
-        score -= (horizontal_check & yellow_pieces).count_ones() as i32 * 42;
-        score -= (vertical_check & yellow_pieces).count_ones() as i32 * 42;
-        score -= (diagonal_check & yellow_pieces).count_ones() as i32 * 42;
-        score -= (antidiagonal_check & yellow_pieces).count_ones() as i32 * 42;
+    /// `evaluate_with` under the default weights, for a game Red opened.
+    pub fn evaluate(&self) -> i32 {
+        let to_move = match self.piece_count() % 2 {
+            0 => Color::Red,
+            _ => Color::Yellow,
+        };
+        self.evaluate_breakdown(to_move).score
+    }
 
-        score
+    /// The evaluation with `to_move` the side to move, which decides who parity favors.
+    pub fn evaluate_with(&self, weights: &EvalWeights, to_move: Color) -> i32 {
+        self.breakdown(weights.reach, to_move).weighted(weights)
     }
 
-    pub fn has_connect_4(&self, color: Color) -> bool {
+    /// The components that make up `evaluate`, for inspecting and tuning the heuristic.
+    pub fn evaluate_breakdown(&self, to_move: Color) -> EvalBreakdown {
+        self.breakdown(None, to_move)
+    }
+
+    /// The potential lines `evaluate` counts for `color`, summed over all four directions.
+    pub fn potential_lines(&self, color: Color) -> u32 {
         let pieces = match color {
             Color::Yellow => self.yellow,
             Color::Red => self.red,
         } & GAME_MASK;
+        direction_counts(pieces, pieces | self.empty()).iter().sum()
+    }
+
+    /// The windows of four that `color` can still complete, by how many of its pieces they
+    /// hold: `[twos, threes, fours]`. A window counts once per direction it runs in.
+    pub fn line_counts(&self, color: Color) -> [u32; 3] {
+        let (pieces, opponent) = match color {
+            Color::Red => (self.red, self.yellow),
+            Color::Yellow => (self.yellow, self.red),
+        };
+        let (pieces, opponent) = (pieces & GAME_MASK, opponent & GAME_MASK);
+
+        let mut counts = [0; 3];
+        for direction in DIRECTIONS {
+            for start in 0..42 {
+                let mut window = 1 << start;
+                let mut cell = window;
+                for _ in 0..3 {
+                    cell = direction.forward(cell) & GAME_MASK;
+                    window |= cell;
+                }
+                if window.count_ones() < 4 || window & opponent != 0 {
+                    continue;
+                }
 
-        let mut horizontal_check = pieces;
-        let mut vertical_check = pieces;
-        let mut diagonal_check = pieces;
-        let mut antidiagonal_check = pieces;
-        for _ in 0..3 {
-            horizontal_check = ((horizontal_check & !FILE[0]) >> 1) & pieces;
-            vertical_check = ((vertical_check & !ROW[5]) >> 7) & pieces;
-            diagonal_check = ((diagonal_check & !ROW[5] & !FILE[6]) >> 6) & pieces;
-            antidiagonal_check = ((antidiagonal_check & !ROW[5] & !FILE[0]) >> 8) & pieces;
+                let held = (window & pieces).count_ones() as usize;
+                if held >= 2 {
+                    counts[held - 2] += 1;
+                }
+            }
         }
 
-        horizontal_check | vertical_check | diagonal_check | antidiagonal_check != 0
+        counts
     }
 
-    pub fn legal_files(&self) -> ArrayVec<usize, 7> {
-        let mut legal_files = ArrayVec::new();
-        let mut top_row = self.empty() & ROW[5];
-        while top_row != 0 {
-            let lsb = top_row & (!top_row + 1);
-            legal_files.push(lsb.trailing_zeros() as usize);
-            top_row &= top_row - 1;
+    /// The empty cells that could be filled within `within_moves` plies: the next
+    /// `within_moves` landing cells of each column. One move reaches exactly `next_cells`.
+    pub fn reachable_empty(&self, within_moves: usize) -> u64 {
+        let mut frontier = self.next_cells();
+        let mut reachable = 0;
+        for _ in 0..within_moves {
+            reachable |= frontier;
+            frontier >>= 7;
         }
 
-        legal_files
+        reachable
     }
-}
 
-impl Color {
-    pub fn other(&self) -> Self {
-        match self {
-            Color::Red => Color::Yellow,
-            Color::Yellow => Color::Red,
-        }
+    fn breakdown(&self, reach: Option<usize>, to_move: Color) -> EvalBreakdown {
+        let red_pieces = self.red & GAME_MASK;
+        let yellow_pieces = self.yellow & GAME_MASK;
+        let empty = match reach {
+            Some(reach) => self.reachable_empty(reach),
+            None => self.empty(),
+        };
+
+        // Potential lines start with the pieces and scan through pieces or empty cells - indicating
+        // the "potential" for a connect-4. The result is ANDed with the original piece positions
+        // to count every piece that is potentially part of a connect-4.
+        // Ideally this should not score piece placements that can never acheive a connect-4; a
+        // `reach` at least drops lines through cells too far above their column's top.
+        let red_potential = direction_counts(red_pieces, red_pieces | empty);
+        let yellow_potential = direction_counts(yellow_pieces, yellow_pieces | empty);
+
+        // Separate scoring for actual connect-4
+        let red_connect_4 = direction_counts(red_pieces, red_pieces);
+        let yellow_connect_4 = direction_counts(yellow_pieces, yellow_pieces);
+
+        // Open threes and parity both start from the threats, which cost more than any other term.
+        let red_threats = self.threats(Color::Red);
+        let yellow_threats = self.threats(Color::Yellow);
+        let next_cells = self.next_cells();
+
+        let mut breakdown = EvalBreakdown {
+            red_potential,
+            yellow_potential,
+            red_connect_4,
+            yellow_connect_4,
+            red_open_threes: (red_threats & next_cells).count_ones(),
+            yellow_open_threes: (yellow_threats & next_cells).count_ones(),
+            parity: parity(red_threats, yellow_threats, self.first_mover(to_move)),
+            center: (self.red & CENTER_STACK).count_ones() as i32
+                - (self.yellow & CENTER_STACK).count_ones() as i32,
+            score: 0,
+        };
+        breakdown.score = breakdown.weighted(&EvalWeights::default());
+
+        breakdown
     }
-}
 
-impl Default for Board {
-    fn default() -> Self {
-        Self::new()
+    /// The number of cells `color` could play right now to complete a connect-4, i.e. threes
+    /// (possibly split) whose fourth cell is playable. These are worth more than potential lines.
+    pub fn open_threes(&self, color: Color) -> u32 {
+        (self.threats(color) & self.next_cells()).count_ones()
     }
-}
 
-impl fmt::Display for Board {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        writeln!(f, "{}", "  1  2  3  4  5  6  7   ".blue())?;
-        for i in 0..42 {
-            if i % 7 == 0 {
-                write!(f, "{}", "|".blue())?;
-            }
-            if self.red & (1 << i) != 0 {
-                write!(f, "{}", " ⬤ ".red())?;
-            } else if self.yellow & (1 << i) != 0 {
-                write!(f, "{}", " ⬤ ".yellow())?;
-            } else {
-                write!(f, "{}", " ◯ ".blue())?;
-            }
-            if i % 7 == 6 {
-                writeln!(f, "{}", "|".blue())?;
-            }
-        }
-        Ok(())
+    /// Returns a playable file in which `color` completes a connect-4.
+    pub fn find_immediate_win(&self, color: Color) -> Option<usize> {
+        let wins = self.threats(color) & self.next_cells();
+        (wins != 0).then(|| wins.trailing_zeros() as usize % 7)
     }
-}
 
-impl fmt::Display for Color {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Color::Red => write!(f, "{}", "R".red()),
-            Color::Yellow => write!(f, "{}", "Y".yellow()),
-        }
+    /// `find_immediate_win` for (Red, Yellow): each side's winning drop if it were to move.
+    pub fn immediate_win_moves(&self) -> (Option<usize>, Option<usize>) {
+        (
+            self.find_immediate_win(Color::Red),
+            self.find_immediate_win(Color::Yellow),
+        )
     }
-}
 
-#[cfg(test)]
-mod test {
-    use super::*;
+    /// Whether `color`, to move, can force a win with at most `moves` threats, assuming the
+    /// opponent always blocks a single threat. A double threat or an immediate win ends the search.
+    pub fn has_forcing_sequence(&self, color: Color, moves: usize) -> bool {
+        if self.find_immediate_win(color).is_some() {
+            return true;
+        }
+        if moves == 0 {
+            return false;
+        }
 
-    #[test]
-    fn inserts_into_empty_board() {
-        let mut board = Board::new();
+        self.legal_files().into_iter().any(|file| {
+            let mut board = *self;
+            board.insert(file, color);
+            if board.find_immediate_win(color.other()).is_some() {
+                return false;
+            }
 
-        board.insert(2, Color::Yellow);
-        assert_eq!(board.yellow, 0x0001FC2000000000);
+            let wins = board.threats(color) & board.next_cells();
+            match wins.count_ones() {
+                0 => false,
+                1 => {
+                    board.insert(wins.trailing_zeros() as usize % 7, color.other());
+                    board.has_forcing_sequence(color, moves - 1)
+                }
+                _ => true,
+            }
+        })
+    }
 
-        board.insert(2, Color::Red);
-        assert_eq!(board.red, 0x0001FC0040000000);
+    /// The files where `color` would connect 4 with its next drop.
+    pub fn threat_columns(&self, color: Color) -> ArrayVec<usize, 7> {
+        let wins = self.threats(color) & self.next_cells();
+        (0..7).filter(|&file| wins & FILE[file] != 0).collect()
+    }
 
-        println!("{}", board);
+    /// How much dropping in `file` helps `color` tactically: the threats it creates for `color`,
+    /// minus the playable threats it hands the opponent, so blocking one counts in its favor.
+    /// A cheap guide for ordering moves.
+    pub fn threats_created_by(&self, file: usize, color: Color) -> i32 {
+        self.threat_deltas(color)
+            .into_iter()
+            .find(|&(f, _)| f == file)
+            .map_or(0, |(_, delta)| delta)
     }
 
-    #[test]
-    fn inserts_into_first_last_files() {
-        let mut board = Board::new();
+    /// `threats_created_by` for each legal file, sharing the work on the current position.
+    fn threat_deltas(&self, color: Color) -> ArrayVec<(usize, i32), 7> {
+        let own = self.threats(color);
+        let opponent = self.threats(color.other());
+        let next_cells = self.next_cells();
+        let playable = (opponent & next_cells).count_ones() as i32;
+
+        self.legal_files()
+            .into_iter()
+            .map(|file| {
+                let mut after = *self;
+                after.insert(file, color);
+                let created = (after.threats(color) & !own).count_ones() as i32;
+                // Taking a cell can only remove opponent threats, never add them.
+                let cell = self.landing_cell(file);
+                let allowed = ((opponent & !cell) & after.next_cells()).count_ones() as i32;
+                (file, created - (allowed - playable))
+            })
+            .collect()
+    }
+
+    /// The legal files, most tactically promising for `color` first by `threats_created_by`,
+    /// ties broken center first.
+    pub fn files_by_threats(&self, color: Color) -> ArrayVec<usize, 7> {
+        let mut deltas = self.threat_deltas(color);
+        deltas.sort_by_key(|&(file, delta)| (-delta, file.abs_diff(3), file));
+        deltas.into_iter().map(|(file, _)| file).collect()
+    }
+
+    /// A file where the opponent can already win and still could after `color` drops in `file`,
+    /// or None if there is no such threat or the drop wins outright.
+    pub fn unblocked_threat(&self, file: usize, color: Color) -> Option<usize> {
+        let threats = self.threat_columns(color.other());
+        if threats.is_empty() {
+            return None;
+        }
+
+        let mut after = *self;
+        after.insert(file, color);
+        if after.has_connect_4(color) {
+            return None;
+        }
+
+        let remaining = after.threat_columns(color.other());
+        threats.into_iter().find(|file| remaining.contains(file))
+    }
+
+    /// A cheap static score for `color` dropping in `file`, for ordering moves without a search.
+    /// Winning outright comes first and handing the opponent a win last; between them, blocks,
+    /// new threats and closeness to the center each add a little. Files that are off the board or
+    /// full are rejected as `try_move` rejects them, since no score would order them safely.
+    pub fn score_move(&self, file: usize, color: Color) -> Result<i32, BoardError> {
+        if file >= FILE.len() {
+            return Err(BoardError::InvalidFile(file));
+        }
+        if self.heights[file] == 6 {
+            return Err(BoardError::FullFile(file));
+        }
+
+        let mut after = *self;
+        after.insert(file, color);
+        if after.has_connect_4(color) {
+            return Ok(1_000_000);
+        }
+
+        let new_threats = |before: u64, after: u64| (after & !before).count_ones() as i32;
+        let mut score = 3 - (file as i32 - 3).abs();
+        score += 100
+            * new_threats(
+                self.threats(color) & self.next_cells(),
+                after.threats(color) & after.next_cells(),
+            );
+        score += 10 * new_threats(self.threats(color), after.threats(color));
+        if self.threat_columns(color.other()).contains(&file) {
+            score += 10_000;
+        }
+        if after.find_immediate_win(color.other()).is_some() {
+            score -= 100_000;
+        }
+
+        Ok(score)
+    }
+
+    /// One bit per non-full file, at the cell the next piece in that file would land in. Every
+    /// stack rests on the sentinel row, so the cell above it is the one above any occupied cell.
+    #[inline(always)]
+    pub fn next_cells(&self) -> u64 {
+        (self.all() >> 7) & !self.all() & GAME_MASK
+    }
+
+    /// The cells `color` can drop into right now to block a line the opponent would complete
+    /// there: the opponent's threats that are playable.
+    pub fn defensive_cells(&self, color: Color) -> u64 {
+        self.threats(color.other()) & self.next_cells()
+    }
+
+    /// The color that opened the game, given `to_move`. Whoever moved first is to move whenever
+    /// the board holds an even number of pieces.
+    fn first_mover(&self, to_move: Color) -> Color {
+        match self.piece_count() % 2 {
+            0 => to_move,
+            _ => to_move.other(),
+        }
+    }
+
+    /// Splits `color`'s threats by row parity with `to_move` the side to move.
+    pub fn parity_summary(&self, color: Color, to_move: Color) -> ParityInfo {
+        let threats = self.threats(color);
+        let (favored, rows) = match color == self.first_mover(to_move) {
+            true => (RowParity::Odd, ODD_ROWS),
+            false => (RowParity::Even, EVEN_ROWS),
+        };
+
+        ParityInfo {
+            odd: (threats & ODD_ROWS).count_ones(),
+            even: (threats & EVEN_ROWS).count_ones(),
+            favored,
+            key_files: (0..7)
+                .filter(|&file| threats & rows & FILE[file] != 0)
+                .collect(),
+        }
+    }
+
+    /// Empty cells that would complete a connect-4 for `color`, whether or not they are playable.
+    pub fn threats(&self, color: Color) -> u64 {
+        let pieces = match color {
+            Color::Yellow => self.yellow,
+            Color::Red => self.red,
+        } & GAME_MASK;
+
+        // A cell is a threat if three pieces line up with it in any arrangement along a direction.
+        let mut threats = 0;
+        for direction in DIRECTIONS {
+            let (forward, backward) = (|x| direction.forward(x), |x| direction.backward(x));
+            threats |= backward(pieces & backward(pieces & backward(pieces)));
+            threats |= forward(pieces & forward(pieces & forward(pieces)));
+            threats |= forward(pieces) & backward(pieces & backward(pieces));
+            threats |= forward(pieces & forward(pieces)) & backward(pieces);
+        }
+
+        threats & self.empty() & GAME_MASK
+    }
+
+    /// Steps `pieces` forward along `direction` `steps` times, keeping only the cells that land
+    /// in `constrain` each step. Cells never wrap across an edge. With `pieces` and `constrain`
+    /// both a color's pieces, `steps` of 3 leaves the far ends of its lines of four.
+    #[inline(always)]
+    pub fn scan(pieces: u64, direction: Direction, steps: u32, constrain: u64) -> u64 {
+        let mut cells = pieces;
+        for _ in 0..steps {
+            cells = direction.forward(cells) & constrain;
+        }
+        cells
+    }
+
+    pub fn has_connect_4(&self, color: Color) -> bool {
+        let pieces = match color {
+            Color::Yellow => self.yellow,
+            Color::Red => self.red,
+        } & GAME_MASK;
+
+        DIRECTIONS
+            .into_iter()
+            .any(|direction| Board::scan(pieces, direction, 3, pieces) != 0)
+    }
+
+    /// True when every window of four cells holds pieces of both colors, so neither side can
+    /// ever connect 4 and the game is a dead draw.
+    pub fn no_winning_chances(&self) -> bool {
+        let empty = self.empty() & GAME_MASK;
+
+        [self.red & GAME_MASK, self.yellow & GAME_MASK]
+            .into_iter()
+            .all(|pieces| {
+                let open = pieces | empty;
+                DIRECTIONS
+                    .into_iter()
+                    .all(|direction| Board::scan(open, direction, 3, open) == 0)
+            })
+    }
+
+    /// The number of distinct lines of four `color` has, so a five in a row counts twice.
+    pub fn connect4_count(&self, color: Color) -> u32 {
+        self.connect_n_count(color, 4)
+    }
+
+    /// The number of distinct lines of `n` in a row `color` has, for Connect-N variants.
+    pub fn connect_n_count(&self, color: Color, n: usize) -> u32 {
+        let pieces = match color {
+            Color::Red => self.red,
+            Color::Yellow => self.yellow,
+        } & GAME_MASK;
+
+        // Each line has exactly one far end.
+        DIRECTIONS
+            .into_iter()
+            .map(|direction| {
+                Board::scan(pieces, direction, (n as u32).saturating_sub(1), pieces).count_ones()
+            })
+            .sum()
+    }
+
+    /// Whether `color` has `n` in a row. `has_connect_4` is the faster special case.
+    pub fn has_connect_n(&self, color: Color, n: usize) -> bool {
+        match n {
+            4 => self.has_connect_4(color),
+            n => self.connect_n_count(color, n) > 0,
+        }
+    }
+
+    /// A file where `color` would make `n` in a row with its next drop.
+    pub fn find_immediate_win_n(&self, color: Color, n: usize) -> Option<usize> {
+        if n == 4 {
+            return self.find_immediate_win(color);
+        }
+
+        self.legal_files().into_iter().find(|&file| {
+            let mut board = *self;
+            board.insert(file, color);
+            board.has_connect_n(color, n)
+        })
+    }
+
+    /// A rough evaluation for Connect-N, positive favoring Red: completed lines of `n` weigh
+    /// `connect_4` each and every shorter run of two or more weighs `potential`. Connect-4 uses
+    /// the full `evaluate_with` instead.
+    pub fn evaluate_connect_n(&self, n: usize, weights: &EvalWeights, to_move: Color) -> i32 {
+        if n == 4 {
+            return self.evaluate_with(weights, to_move);
+        }
+
+        let lines = |length: usize| {
+            self.connect_n_count(Color::Red, length) as i32
+                - self.connect_n_count(Color::Yellow, length) as i32
+        };
+        lines(n) * weights.connect_4 + (2..n).map(lines).sum::<i32>() * weights.potential
+    }
+
+    /// For each cell index (0-41, top-left first), how many windows of four through that cell
+    /// `color` could still complete, i.e. that hold no opposing piece. Occupied cells score 0.
+    pub fn heatmap(&self, color: Color) -> [i32; 42] {
+        let opponent = match color {
+            Color::Red => self.yellow,
+            Color::Yellow => self.red,
+        } & GAME_MASK;
+        let empty = self.empty() & GAME_MASK;
+
+        let mut heatmap = [0; 42];
+        for direction in DIRECTIONS {
+            for start in 0..42 {
+                let mut window = 1 << start;
+                let mut cell = window;
+                for _ in 0..3 {
+                    cell = direction.forward(cell) & GAME_MASK;
+                    window |= cell;
+                }
+                if window.count_ones() < 4 || window & opponent != 0 {
+                    continue;
+                }
+
+                let mut open = window & empty;
+                while open != 0 {
+                    heatmap[open.trailing_zeros() as usize] += 1;
+                    open &= open - 1;
+                }
+            }
+        }
+
+        heatmap
+    }
+
+    /// Every cell that is part of a connect-4, for either color.
+    pub fn winning_cells(&self) -> u64 {
+        let mut winning = 0;
+        for pieces in [self.red & GAME_MASK, self.yellow & GAME_MASK] {
+            for direction in DIRECTIONS {
+                // The far ends of runs of four, then walked back over the rest of each run.
+                let mut ends = Board::scan(pieces, direction, 3, pieces);
+                for _ in 0..4 {
+                    winning |= ends;
+                    ends = direction.backward(ends);
+                }
+            }
+        }
+
+        winning
+    }
+
+    /// The four cells of the first connect-4 for `color`, scanning directions in the usual order
+    /// and lowest cell index first, or 0 if there is none.
+    pub fn winning_line_mask(&self, color: Color) -> u64 {
+        let pieces = match color {
+            Color::Red => self.red,
+            Color::Yellow => self.yellow,
+        } & GAME_MASK;
+
+        for direction in DIRECTIONS {
+            let ends = Board::scan(pieces, direction, 3, pieces);
+            if ends == 0 {
+                continue;
+            }
+
+            let mut cell = ends & ends.wrapping_neg();
+            let mut line = cell;
+            for _ in 0..3 {
+                cell = direction.backward(cell);
+                line |= cell;
+            }
+            return line;
+        }
+
+        0
+    }
+
+    /// Whether the top piece of `file` is part of a connect-4, checking only the lines through it.
+    pub fn last_move_wins(&self, file: usize) -> bool {
+        let cell = match self.heights[file] {
+            0 => return false,
+            height => 1 << ((6 - height as usize) * 7 + file),
+        };
+        let pieces = match self.red & cell {
+            0 => self.yellow,
+            _ => self.red,
+        } & GAME_MASK;
+
+        DIRECTIONS.into_iter().any(|direction| {
+            let mut line = cell;
+            for _ in 0..3 {
+                line |= (direction.forward(line) | direction.backward(line)) & pieces;
+            }
+            line.count_ones() >= 4
+        })
+    }
+
+    /// Plays uniformly random moves until someone connects 4 or the board fills, returning the
+    /// winner.
+    pub fn random_playout(&mut self, to_move: Color, rng: &mut impl Rng) -> Option<Color> {
+        let mut color = to_move;
+        loop {
+            let files = self.legal_files();
+            if files.is_empty() {
+                return None;
+            }
+
+            let file = files[rng.gen_range(0..files.len())];
+            self.insert(file, color);
+            if self.last_move_wins(file) {
+                return Some(color);
+            }
+            color = color.other();
+        }
+    }
+
+    /// Each legal file for `color` with its evaluation searched to `depth`, in file order. This is
+    /// `Minimax::analyze` sorted by file. The heuristic search keeps no transposition table, so
+    /// each move is searched on its own and nothing is shared between them.
+    pub fn successor_evaluations(&self, color: Color, depth: usize) -> ArrayVec<(usize, i32), 7> {
+        let mut evaluations = Minimax::new(self, color, depth)
+            .analyze()
+            .into_iter()
+            .collect::<ArrayVec<_, 7>>();
+        evaluations.sort_unstable_by_key(|&(file, _)| file);
+
+        evaluations
+    }
+
+    /// The legal files for `color` without obvious blunders: only a winning move if there is
+    /// one, otherwise the moves that don't let the opponent win straight away. If every move does,
+    /// all of them.
+    pub fn safe_legal_files(&self, color: Color) -> ArrayVec<usize, 7> {
+        if let Some(file) = self.find_immediate_win(color) {
+            return [file].into_iter().collect();
+        }
+
+        let files = self.legal_files();
+        let safe = files
+            .iter()
+            .copied()
+            .filter(|&file| {
+                let mut board = *self;
+                board.insert(file, color);
+                board.find_immediate_win(color.other()).is_none()
+            })
+            .collect::<ArrayVec<_, 7>>();
+
+        match safe.is_empty() {
+            true => files,
+            false => safe,
+        }
+    }
+
+    /// The files with no room left, the complement of `legal_files`.
+    pub fn full_columns(&self) -> ArrayVec<usize, 7> {
+        (0..7).filter(|&file| self.height(file) == 6).collect()
+    }
+
+    /// The files with room for another piece, always in ascending order (left to right).
+    ///
+    /// Searches, tests and seeded games rely on this order, so it is produced by walking the
+    /// files explicitly rather than falling out of the bit layout.
+    pub fn legal_files(&self) -> ArrayVec<usize, 7> {
+        (0..7).filter(|&file| self.height(file) < 6).collect()
+    }
+}
+
+/// Claimeven-style tie-breaker, from each color's `Board::threats`. With no other tactics, the
+/// `first` mover ends up claiming the odd cells of contested columns and the other color the even
+/// ones, so only threats on those rows count.
+///
+/// This is the only part of `evaluate` that depends on who opened, and that is intentional: the
+/// two colors' threats on the same row are not worth the same.
+fn parity(red_threats: u64, yellow_threats: u64, first: Color) -> i32 {
+    let (red_rows, yellow_rows) = match first {
+        Color::Red => (ODD_ROWS, EVEN_ROWS),
+        Color::Yellow => (EVEN_ROWS, ODD_ROWS),
+    };
+    (red_threats & red_rows).count_ones() as i32
+        - (yellow_threats & yellow_rows).count_ones() as i32
+}
+
+/// Counts the pieces that end a line of four running through `constrain` in each direction:
+/// horizontal, vertical, diagonal and antidiagonal.
+fn direction_counts(pieces: u64, constrain: u64) -> [u32; 4] {
+    DIRECTIONS.map(|direction| (Board::scan(pieces, direction, 3, constrain) & pieces).count_ones())
+}
+
+impl Color {
+    pub fn other(&self) -> Self {
+        match self {
+            Color::Red => Color::Yellow,
+            Color::Yellow => Color::Red,
+        }
+    }
+}
+
+impl Default for Board {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Board {
+    /// Renders the board as `Display` does, or rotated half a turn when `flipped` to show it from
+    /// the opposite side. Column labels always read 1-7 from the left.
+    pub fn render(&self, flipped: bool) -> String {
+        self.render_highlighting(0, flipped, false)
+    }
+
+    /// Renders like `render` or `render_plain`, drawing the pieces in `mask` with a brighter glyph,
+    /// e.g. to pick out the `winning_line_mask`.
+    pub fn render_highlighting(&self, mask: u64, flipped: bool, plain: bool) -> String {
+        let mut rendered = String::new();
+        // Writing to a String can't fail.
+        let _ = self.write_grid(&mut rendered, flipped, plain, mask);
+        rendered
+    }
+
+    /// A plain grid comparing this board with `other`: cells that hold the same thing in both
+    /// show it as `render_plain` would, and cells that differ show `*`. For debugging.
+    pub fn render_diff(&self, other: &Board) -> String {
+        let mut rendered = String::from(" ");
+        for column in 0..7 {
+            rendered += &format!(" {} ", column + 1);
+        }
+        rendered += "  \n";
+
+        for i in 0..42 {
+            if i % 7 == 0 {
+                rendered.push('|');
+            }
+            let bit = 1 << i;
+            let cell = |board: &Board| (board.red & bit, board.yellow & bit);
+            rendered += match (cell(self), cell(other)) {
+                (ours, theirs) if ours != theirs => " * ",
+                ((0, 0), _) => " . ",
+                ((0, _), _) => " Y ",
+                _ => " R ",
+            };
+            if i % 7 == 6 {
+                rendered += "|\n";
+            }
+        }
+
+        rendered
+    }
+
+    /// Like `render`, but with `R`/`Y`/`.` in place of colored discs and no escape codes at all,
+    /// regardless of the `colored` crate's override.
+    pub fn render_plain(&self, flipped: bool) -> String {
+        self.render_highlighting(0, flipped, true)
+    }
+
+    pub fn to_string_plain(&self) -> String {
+        self.render_plain(false)
+    }
+
+    /// A grid of emoji for pasting into chat, top row first, with pieces in a connect-4 shown as
+    /// squares instead of circles.
+    pub fn to_emoji_grid(&self) -> String {
+        let winning = self.winning_cells();
+
+        (0..6)
+            .map(|row| {
+                (0..7)
+                    .map(|file| {
+                        let cell = 1 << (row * 7 + file);
+                        match (self.red & cell, self.yellow & cell, winning & cell) {
+                            (0, 0, _) => '⚪',
+                            (0, _, 0) => '🟡',
+                            (0, _, _) => '🟨',
+                            (_, _, 0) => '🔴',
+                            (_, _, _) => '🟥',
+                        }
+                    })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// A self-contained SVG of the board, blue with red, yellow and white discs, for web pages
+    /// and docs. Pieces in a connect-4 get a white ring, as `to_emoji_grid` squares them.
+    pub fn to_svg(&self) -> String {
+        let winning = self.winning_cells();
+        let mut svg = String::from(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"700\" height=\"600\" \
+             viewBox=\"0 0 700 600\">\n<rect width=\"700\" height=\"600\" rx=\"20\" fill=\"#1e4fd8\"/>\n",
+        );
+
+        for row in 0..6 {
+            for file in 0..7 {
+                let cell = 1 << (row * 7 + file);
+                let fill = match (self.red & cell, self.yellow & cell) {
+                    (0, 0) => "#ffffff",
+                    (0, _) => "#f5d000",
+                    _ => "#e02020",
+                };
+                let ring = match winning & cell {
+                    0 => "",
+                    _ => " stroke=\"#ffffff\" stroke-width=\"8\"",
+                };
+                svg.push_str(&format!(
+                    "<circle cx=\"{}\" cy=\"{}\" r=\"40\" fill=\"{}\"{}/>\n",
+                    file * 100 + 50,
+                    row * 100 + 50,
+                    fill,
+                    ring
+                ));
+            }
+        }
+
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    fn write_grid(
+        &self,
+        f: &mut impl fmt::Write,
+        flipped: bool,
+        plain: bool,
+        highlight: u64,
+    ) -> fmt::Result {
+        // Full files get a dimmed number, since nothing more can be dropped there.
+        let full = self.full_columns();
+        write!(f, " ")?;
+        for column in 0..7 {
+            let file = match flipped {
+                true => Board::mirror_file(column),
+                false => column,
+            };
+            let label = format!(" {} ", column + 1);
+            match (plain, full.contains(&file)) {
+                (true, _) => write!(f, "{}", label)?,
+                (false, false) => write!(f, "{}", label.blue())?,
+                (false, true) => write!(f, "{}", label.dimmed())?,
+            }
+        }
+        writeln!(f, "  ")?;
+
+        for i in 0..42 {
+            let cell = match flipped {
+                true => 41 - i,
+                false => i,
+            };
+            if i % 7 == 0 {
+                match plain {
+                    true => write!(f, "|")?,
+                    false => write!(f, "{}", "|".blue())?,
+                }
+            }
+            let bit = 1 << cell;
+            match (plain, self.red & bit, self.yellow & bit, highlight & bit) {
+                (true, 0, 0, _) => write!(f, " . ")?,
+                (true, 0, _, 0) => write!(f, " Y ")?,
+                (true, 0, _, _) => write!(f, "[Y]")?,
+                (true, _, _, 0) => write!(f, " R ")?,
+                (true, _, _, _) => write!(f, "[R]")?,
+                (false, 0, 0, _) => write!(f, "{}", " ◯ ".blue())?,
+                (false, 0, _, 0) => write!(f, "{}", " ⬤ ".yellow())?,
+                (false, 0, _, _) => write!(f, "{}", " ◆ ".bright_yellow().bold())?,
+                (false, _, _, 0) => write!(f, "{}", " ⬤ ".red())?,
+                (false, _, _, _) => write!(f, "{}", " ◆ ".bright_red().bold())?,
+            }
+            if i % 7 == 6 {
+                match plain {
+                    true => writeln!(f, "|")?,
+                    false => writeln!(f, "{}", "|".blue())?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Board {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_grid(f, false, false, 0)
+    }
+}
+
+impl From<&Board> for PositionKey {
+    fn from(board: &Board) -> Self {
+        PositionKey(board.to_compact())
+    }
+}
+
+impl From<PositionKey> for u64 {
+    fn from(key: PositionKey) -> Self {
+        key.0
+    }
+}
+
+impl TryFrom<PositionKey> for Board {
+    type Error = BoardError;
+
+    fn try_from(key: PositionKey) -> Result<Self, BoardError> {
+        Board::from_compact(key.0)
+    }
+}
+
+impl fmt::Display for BoardError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BoardError::InvalidFile(file) => write!(f, "invalid file index: {}", file),
+            BoardError::EmptyFile(file) => write!(f, "file {} is empty", file + 1),
+            BoardError::FullFile(file) => write!(f, "file {} is full", file + 1),
+            BoardError::InvalidCompact(code) => write!(f, "invalid compact code: {:#x}", code),
+            BoardError::Overlapping(cells) => write!(f, "cells hold both colors: {:#x}", cells),
+            BoardError::OffBoard(bits) => write!(f, "bits set off the board: {:#x}", bits),
+            BoardError::MissingSentinel => write!(f, "the sentinel row is not set"),
+            BoardError::Floating(cells) => {
+                write!(f, "pieces float above empty cells: {:#x}", cells)
+            }
+            BoardError::Unbalanced(red, yellow) => {
+                write!(f, "{} red pieces against {} yellow", red, yellow)
+            }
+            BoardError::InvalidCell(row, file) => {
+                write!(f, "invalid cell: row {}, file {}", row + 1, file + 1)
+            }
+            BoardError::InvalidNotation(line, e) => write!(f, "line {}: {}", line, e),
+            BoardError::Io(e) => write!(f, "could not read positions: {}", e),
+            BoardError::InvalidAscii(e) => write!(f, "invalid diagram: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for BoardError {}
+
+impl fmt::Display for RowParity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RowParity::Odd => write!(f, "odd"),
+            RowParity::Even => write!(f, "even"),
+        }
+    }
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Color::Red => write!(f, "{}", "R".red()),
+            Color::Yellow => write!(f, "{}", "Y".yellow()),
+        }
+    }
+}
+
+#[cfg(test)]
+impl Board {
+    /// A random position reachable in play, for fuzzing: up to `max_pieces` random drops from the
+    /// empty board, Red first, stopping early if one wins.
+    pub(crate) fn random_legal(rng: &mut impl Rng, max_pieces: usize) -> Board {
+        let mut board = Board::new();
+        let mut color = Color::Red;
+        for _ in 0..rng.gen_range(0..=max_pieces) {
+            let files = board.legal_files();
+            if files.is_empty() {
+                break;
+            }
+            let file = files[rng.gen_range(0..files.len())];
+            board.insert(file, color);
+            if board.last_move_wins(file) {
+                break;
+            }
+            color = color.other();
+        }
+
+        board
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{SeedableRng, rngs::StdRng};
+
+    use crate::constants::{ANTIDIAGONAL, DIAGONAL, HORIZONTAL, VERTICAL};
+
+    use super::*;
+
+    #[test]
+    fn random_positions_are_reachable() {
+        let mut rng = StdRng::seed_from_u64(410);
+        let mut won = 0;
+        for _ in 0..2000 {
+            let max_pieces = rng.gen_range(0..=42);
+            let board = Board::random_legal(&mut rng, max_pieces);
+
+            assert!(board.is_legal(), "{}", board.to_notation());
+            assert!(board.piece_count() <= max_pieces);
+            // Only the side that moved last can have won, and play stops at its first line.
+            let last = match board.piece_count() % 2 {
+                1 => Color::Red,
+                _ => Color::Yellow,
+            };
+            assert!(!board.has_connect_4(last.other()));
+            won += board.has_connect_4(last) as usize;
+        }
+        assert!(won > 0);
+    }
+
+    #[test]
+    fn scores_threats_created_by_a_move() {
+        // Red's third in a row on the bottom makes threats at both ends.
+        let board = Board::from_notation("7/7/7/7/3y3/2rr3").unwrap();
+        assert_eq!(board.threats_created_by(4, Color::Red), 2);
+        assert_eq!(board.threats_created_by(6, Color::Red), 0);
+
+        // Blocking Yellow's playable threat counts for Red, as does Red's own new three.
+        let board = Board::from_notation("7/7/7/7/r6/yyy1rr1").unwrap();
+        assert_eq!(board.threats_created_by(3, Color::Red), 2);
+        assert_eq!(board.files_by_threats(Color::Red)[0], 3);
+
+        // Dropping beneath Yellow's threat lets Yellow play it.
+        let board = Board::from_notation("7/7/7/7/yyy4/rry1y2").unwrap();
+        assert_eq!(board.threats_created_by(3, Color::Red), -1);
+    }
+
+    #[test]
+    fn renders_svg() {
+        let svg = Board::from_notation("7/7/7/7/3y3/2rr3").unwrap().to_svg();
+
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert!(svg.ends_with("</svg>\n"));
+        assert_eq!(svg.matches("<circle ").count(), 42);
+        assert_eq!(svg.matches("fill=\"#e02020\"").count(), 2);
+        assert_eq!(svg.matches("fill=\"#f5d000\"").count(), 1);
+        assert_eq!(svg.matches("fill=\"#ffffff\"").count(), 39);
+        // The bottom row's third cell, from the top-left corner.
+        assert!(svg.contains("<circle cx=\"250\" cy=\"550\" r=\"40\" fill=\"#e02020\"/>"));
+        assert!(!svg.contains("stroke"));
+
+        let won = Board::from_notation("7/7/7/7/yyy4/rrrr3").unwrap().to_svg();
+        assert_eq!(won.matches("stroke=\"#ffffff\"").count(), 4);
+    }
+
+    #[test]
+    fn reports_file_heights() {
+        let board = Board::from_notation("7/7/3y3/2ry3/1yrr3/ryyrr2").unwrap();
+        assert_eq!(board.heights(), [1, 2, 3, 4, 1, 0, 0]);
+        assert_eq!(Board::new().heights(), [0; 7]);
+    }
+
+    #[test]
+    fn height_cache_matches_bitboards() {
+        let fresh = |board: &Board| {
+            (0..7)
+                .map(|file| (FILE[file] & board.all() & GAME_MASK).count_ones() as u8)
+                .collect::<Vec<_>>()
+        };
+        let mut rng = StdRng::seed_from_u64(361);
+
+        for _ in 0..200 {
+            let mut board = Board::random_legal(&mut rng, 42);
+            assert_eq!(board.heights.to_vec(), fresh(&board));
+
+            for derived in [
+                Board::from_notation(&board.to_notation()).unwrap(),
+                Board::from_compact(board.to_compact()).unwrap(),
+                board.mirror_horizontal(),
+                board.flip_colors(),
+            ] {
+                assert_eq!(derived.heights.to_vec(), fresh(&derived));
+            }
+
+            // The search's push/pop pattern, including pushes into full files.
+            for _ in 0..20 {
+                let file = rng.gen_range(0..7);
+                match rng.gen_bool(0.5) {
+                    true => board.insert(file, Color::Red),
+                    false => {
+                        let _ = board.remove(file);
+                    }
+                }
+                assert_eq!(board.heights.to_vec(), fresh(&board));
+            }
+        }
+    }
+
+    #[test]
+    fn remove_undoes_insert_exactly() {
+        let mut rng = StdRng::seed_from_u64(383);
+
+        for _ in 0..2000 {
+            let board = Board::random_legal(&mut rng, 42);
+            let color = match board.piece_count() % 2 {
+                0 => Color::Red,
+                _ => Color::Yellow,
+            };
+
+            for file in 0..7 {
+                let mut pushed = board;
+                pushed.insert(file, color);
+
+                match board.height(file) {
+                    6 => assert_eq!(pushed, board, "insert into full file {}", file),
+                    _ => {
+                        assert_eq!(pushed.remove(file), Ok(color));
+                        assert_eq!(pushed, board, "insert then remove in file {}", file);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn next_cells_match_legal_files() {
+        let mut rng = StdRng::seed_from_u64(362);
+
+        for _ in 0..200 {
+            let board = Board::random_legal(&mut rng, 42);
+            let expected = board
+                .legal_files()
+                .iter()
+                .fold(0, |cells, &file| cells | board.landing_cell(file));
+
+            assert_eq!(board.next_cells(), expected);
+        }
+
+        assert_eq!(Board::new().next_cells(), ROW[0]);
+    }
+
+    #[test]
+    fn renders_emoji_grid() {
+        let board = Board::from_notation("7/7/7/7/3y3/2rr3").unwrap();
+        assert_eq!(
+            board.to_emoji_grid(),
+            "⚪⚪⚪⚪⚪⚪⚪\n\
+             ⚪⚪⚪⚪⚪⚪⚪\n\
+             ⚪⚪⚪⚪⚪⚪⚪\n\
+             ⚪⚪⚪⚪⚪⚪⚪\n\
+             ⚪⚪⚪🟡⚪⚪⚪\n\
+             ⚪⚪🔴🔴⚪⚪⚪"
+        );
+
+        let board = Board::from_notation("7/7/7/7/yyy4/1rrrr2").unwrap();
+        assert_eq!(board.to_emoji_grid().lines().last(), Some("⚪🟥🟥🟥🟥⚪⚪"));
+    }
+
+    #[test]
+    fn parses_many_positions() {
+        let fixture = "# opening positions\n\
+                       7/7/7/7/7/7\n\
+                       \n\
+                       7/7/7/7/3y3/2rr3\n\
+                       7/7/x\n";
+
+        let boards = Board::parse_many(fixture.as_bytes()).collect::<Vec<_>>();
+
+        assert_eq!(boards.len(), 3);
+        assert_eq!(boards[0], Ok(Board::new()));
+        assert_eq!(
+            boards[1],
+            Ok(Board::from_notation("7/7/7/7/3y3/2rr3").unwrap())
+        );
+        assert!(matches!(boards[2], Err(BoardError::InvalidNotation(5, _))));
+    }
+
+    #[test]
+    fn counts_connect_4s() {
+        let four = Board::from_notation("7/7/7/7/yyy4/rrrr3").unwrap();
+        assert_eq!(four.connect4_count(Color::Red), 1);
+        assert_eq!(four.connect4_count(Color::Yellow), 0);
+
+        let five = Board::from_notation("7/7/7/7/yyyy3/rrrrr2").unwrap();
+        assert_eq!(five.connect4_count(Color::Red), 2);
+        assert_eq!(five.connect4_count(Color::Yellow), 1);
+
+        // A vertical and a horizontal four sharing a corner.
+        let cross = Board::from_notation("7/7/r6/r6/ryyy3/rrrry2").unwrap();
+        assert_eq!(cross.connect4_count(Color::Red), 2);
+
+        assert_eq!(Board::new().connect4_count(Color::Red), 0);
+    }
+
+    #[test]
+    fn detects_connect_n() {
+        let board = Board::from_moves("17273").unwrap();
+
+        assert!(board.has_connect_n(Color::Red, 3));
+        assert!(!board.has_connect_n(Color::Red, 4));
+        assert!(!board.has_connect_n(Color::Yellow, 3));
+        assert_eq!(board.connect_n_count(Color::Red, 2), 2);
+        assert_eq!(
+            Board::from_moves("1727")
+                .unwrap()
+                .find_immediate_win_n(Color::Red, 3),
+            Some(2)
+        );
+        assert!(board.evaluate_connect_n(3, &EvalWeights::default(), Color::Red) > 0);
+    }
+
+    #[test]
+    fn counts_completable_lines_by_length() {
+        let cases = [
+            ("7/7/7/7/7/7", Color::Red, [0, 0, 0]),
+            ("7/7/7/7/7/rr5", Color::Red, [1, 0, 0]),
+            ("7/7/7/7/7/rrr4", Color::Red, [1, 1, 0]),
+            ("7/7/7/r6/r6/r6", Color::Red, [1, 1, 0]),
+            ("7/7/7/7/7/rrrr3", Color::Red, [1, 1, 1]),
+            // A yellow piece spoils every window it sits in.
+            ("7/7/7/7/7/rrry3", Color::Red, [0, 0, 0]),
+            ("7/7/7/7/7/rrry3", Color::Yellow, [0, 0, 0]),
+            ("7/7/7/7/y6/yrr4", Color::Yellow, [1, 0, 0]),
+        ];
+        for (notation, color, expected) in cases {
+            let board = Board::from_notation(notation).unwrap();
+            assert_eq!(
+                board.line_counts(color),
+                expected,
+                "{} {:?}",
+                notation,
+                color
+            );
+        }
+    }
+
+    #[test]
+    fn summarizes_threat_parity() {
+        // Red's three-in-a-rows on rows 1 and 3 both end in file 4; Yellow's on row 2 does too.
+        let board = Board::from_notation("7/7/7/rrr4/yyy1y2/rrr1y1y").unwrap();
+        let red = board.parity_summary(Color::Red, Color::Red);
+        assert_eq!((red.odd, red.even, red.favored), (2, 0, RowParity::Odd));
+        assert_eq!(red.key_files.as_slice(), &[3]);
+        let yellow = board.parity_summary(Color::Yellow, Color::Red);
+        assert_eq!(
+            (yellow.odd, yellow.even, yellow.favored),
+            (0, 1, RowParity::Even)
+        );
+        assert_eq!(yellow.key_files.as_slice(), &[3]);
+
+        // Yellow moved first here, so its odd threat is the key one and Red wants even rows.
+        let board = Board::from_notation("7/7/7/7/rr5/yyy4").unwrap();
+        let yellow = board.parity_summary(Color::Yellow, Color::Red);
+        assert_eq!(
+            (yellow.odd, yellow.even, yellow.favored),
+            (1, 0, RowParity::Odd)
+        );
+        assert_eq!(yellow.key_files.as_slice(), &[3]);
+        let red = board.parity_summary(Color::Red, Color::Red);
+        assert_eq!(red.favored, RowParity::Even);
+        assert!(red.key_files.is_empty());
+    }
+
+    #[test]
+    fn summarizes_threat_parity_when_yellow_moved_first() {
+        // Equal counts with Yellow to move means Yellow moved first, so the parities swap.
+        let board = Board::from_notation("7/7/7/rrr4/yyy1y2/rrr1y1y").unwrap();
+        let red = board.parity_summary(Color::Red, Color::Yellow);
+        assert_eq!((red.odd, red.even, red.favored), (2, 0, RowParity::Even));
+        assert!(red.key_files.is_empty());
+        let yellow = board.parity_summary(Color::Yellow, Color::Yellow);
+        assert_eq!(
+            (yellow.odd, yellow.even, yellow.favored),
+            (0, 1, RowParity::Odd)
+        );
+        assert!(yellow.key_files.is_empty());
+    }
+
+    #[test]
+    fn scans_each_direction_without_wrapping() {
+        // Rows count from the top, as in the bitboards.
+        let cell = |row: usize, file: usize| 1u64 << (row * 7 + file);
+        let line =
+            |cells: [(usize, usize); 4]| cells.iter().fold(0, |line, &(r, f)| line | cell(r, f));
+
+        let cases = [
+            (HORIZONTAL, [(5, 3), (5, 2), (5, 1), (5, 0)]),
+            (VERTICAL, [(5, 0), (4, 0), (3, 0), (2, 0)]),
+            (DIAGONAL, [(5, 0), (4, 1), (3, 2), (2, 3)]),
+            (ANTIDIAGONAL, [(5, 6), (4, 5), (3, 4), (2, 3)]),
+        ];
+        for (direction, cells) in cases {
+            let pieces = line(cells);
+            let (start, end) = (cell(cells[0].0, cells[0].1), cell(cells[3].0, cells[3].1));
+            assert_eq!(Board::scan(pieces, direction, 0, pieces), pieces);
+            assert_eq!(Board::scan(pieces, direction, 3, pieces), end);
+            assert_eq!(Board::scan(start, direction, 3, GAME_MASK), end);
+            assert_eq!(Board::scan(pieces, direction, 4, pieces), 0);
+            // A gap in the line stops the scan.
+            let gapped = pieces & !cell(cells[2].0, cells[2].1);
+            assert_eq!(Board::scan(gapped, direction, 3, gapped), 0);
+            // Each line ends at the top edge or file 0, so carrying on leaves the board.
+            assert_eq!(Board::scan(end, direction, 3, !0), 0);
+        }
+        // Steps off an edge leave nothing, where a raw shift would wrap to the other side.
+        assert_eq!(Board::scan(cell(5, 0), HORIZONTAL, 1, !0), 0);
+        assert_eq!(Board::scan(cell(4, 6), DIAGONAL, 1, !0), 0);
+        assert_eq!(Board::scan(cell(0, 6), DIAGONAL, 1, !0), 0);
+        assert_eq!(Board::scan(cell(2, 0), ANTIDIAGONAL, 1, !0), 0);
+        assert_eq!(Board::scan(cell(0, 4), VERTICAL, 1, !0), 0);
+    }
+
+    #[test]
+    fn finds_defensive_cells() {
+        // Yellow's threat in file 4 is playable, but Red's above it isn't yet.
+        let board = Board::from_notation("7/7/7/rrr4/yyy4/rrry3").unwrap();
+        assert_eq!(board.defensive_cells(Color::Red), 1 << 31);
+        assert_eq!(board.defensive_cells(Color::Yellow), 0);
+
+        let board = Board::from_notation("7/7/7/7/yy5/rrr4").unwrap();
+        assert_eq!(board.defensive_cells(Color::Yellow), 1 << 38);
+        assert_eq!(board.defensive_cells(Color::Red), 0);
+    }
+
+    #[test]
+    fn heatmap_favors_center() {
+        let heatmap = Board::new().heatmap(Color::Red);
+
+        // The classic 3-4-5-7-5-4-3 table for the bottom row, and 13 at the middle.
+        assert_eq!(heatmap[35..42], [3, 4, 5, 7, 5, 4, 3]);
+        assert_eq!(heatmap[2 * 7 + 3], 13);
+        assert!(heatmap[2 * 7 + 3] > heatmap[0]);
+        assert_eq!(heatmap.iter().sum::<i32>(), 69 * 4);
+
+        let board = Board::from_notation("7/7/7/7/7/3y3").unwrap();
+        let heatmap = board.heatmap(Color::Red);
+        assert_eq!(heatmap[38], 0);
+        assert!(heatmap[37] < 5);
+    }
+
+    #[test]
+    fn masks_first_winning_line() {
+        let board = Board::from_notation("7/7/7/7/yyy4/1rrrrr1").unwrap();
+
+        let mask = board.winning_line_mask(Color::Red);
+        assert_eq!(mask.count_ones(), 4);
+        assert_eq!(mask & board.winning_cells(), mask);
+        assert_eq!(board.winning_line_mask(Color::Yellow), 0);
+        assert_eq!(Board::new().winning_line_mask(Color::Red), 0);
+
+        let rendered = board.render_highlighting(mask, false, true);
+        assert_eq!(rendered.matches("[R]").count(), 4);
+        assert_eq!(rendered.lines().last(), Some("| . [R][R][R][R] R  . |"));
+    }
+
+    #[test]
+    fn mirrors_files() {
+        assert_eq!(Board::mirror_file(0), 6);
+        assert_eq!(Board::mirror_file(6), 0);
+        assert_eq!(Board::mirror_file(3), 3);
+        for file in 0..7 {
+            assert_eq!(Board::mirror_file(Board::mirror_file(file)), file);
+
+            let board = Board::from_moves(&(file + 1).to_string()).unwrap();
+            assert_eq!(
+                board.mirror_horizontal().height(Board::mirror_file(file)),
+                1
+            );
+        }
+    }
+
+    #[test]
+    fn position_keys_identify_positions() {
+        let mut rng = StdRng::seed_from_u64(428);
+        let mut boards = std::collections::HashMap::new();
+        for _ in 0..500 {
+            let board = Board::random_legal(&mut rng, 42);
+            let key = PositionKey::from(&board);
+            assert_eq!(Board::try_from(key), Ok(board));
+            assert_eq!(*boards.entry(key).or_insert(board), board);
+        }
+        assert!(boards.len() > 400);
+
+        // Transposed move orders reach the same position.
+        let key = |moves: &str| PositionKey::from(&Board::from_moves(moves).unwrap());
+        assert_eq!(key("1234"), key("3214"));
+        assert_ne!(key("1234"), key("2143"));
+        assert_ne!(key(""), key("4"));
+    }
+
+    #[test]
+    fn round_trips_raw_bits() {
+        let mut rng = StdRng::seed_from_u64(377);
+        for _ in 0..100 {
+            let board = Board::random_legal(&mut rng, 42);
+            assert_eq!(
+                Board::from_bits(board.red_bits(), board.yellow_bits()),
+                Ok(board)
+            );
+        }
+
+        let overlapping = EMPTY_BOARD | 1 << 38;
+        assert_eq!(
+            Board::from_bits(overlapping, overlapping),
+            Err(BoardError::Overlapping(1 << 38))
+        );
+        assert!(Board::from_bits(0, 0).is_err());
+        assert!(Board::from_bits(EMPTY_BOARD | 1 << 60, EMPTY_BOARD).is_err());
+    }
+
+    #[test]
+    fn validates_positions() {
+        let board = Board::from_moves("4453").unwrap();
+        assert_eq!(board.validate(), Ok(()));
+        assert_eq!(Board::new().validate(), Ok(()));
+
+        let bits = |red: u64, yellow: u64| Board {
+            red,
+            yellow,
+            heights: [0; 7],
+        };
+        let (red, yellow) = (board.red_bits(), board.yellow_bits());
+        // Row 5 is the bottom row, so cell 38 is the bottom of file 3 and cell 31 sits on it.
+        assert_eq!(
+            bits(red | 1 << 31, yellow | 1 << 31).validate(),
+            Err(BoardError::Overlapping(1 << 31))
+        );
+        assert_eq!(
+            bits(red | 1 << 55, yellow).validate(),
+            Err(BoardError::OffBoard(1 << 55))
+        );
+        assert_eq!(
+            bits(red & !(1 << 44), yellow).validate(),
+            Err(BoardError::MissingSentinel)
+        );
+        assert_eq!(
+            bits(red | 1 << 3, yellow).validate(),
+            Err(BoardError::Floating(1 << 3))
+        );
+        assert_eq!(
+            Board::from_notation("7/7/7/7/7/rrr4").unwrap().validate(),
+            Err(BoardError::Unbalanced(3, 0))
+        );
+
+        assert!(Board::from_notation_strict("7/7/2r4/7/7/7").is_err());
+        assert_eq!(
+            Board::from_notation_strict("7/7/7/7/3y3/3rr2").unwrap(),
+            Board::from_notation("7/7/7/7/3y3/3rr2").unwrap()
+        );
+    }
+
+    #[test]
+    fn places_pieces_anywhere() {
+        let mut board = Board::new();
+
+        board.place(3, 2, Color::Red).unwrap();
+        board.place(0, 6, Color::Yellow).unwrap();
+        assert_eq!(board.piece_at(3, 2), Some(Color::Red));
+        assert_eq!(board.piece_at(0, 6), Some(Color::Yellow));
+        assert_eq!(board.piece_at(0, 2), None);
+        assert_eq!(board, Board::from_notation("7/7/2r4/7/7/6y").unwrap());
+        assert!(!board.is_legal());
+
+        board.place(3, 2, Color::Yellow).unwrap();
+        assert_eq!(board.piece_at(3, 2), Some(Color::Yellow));
+        assert_eq!(
+            board.place(6, 0, Color::Red),
+            Err(BoardError::InvalidCell(6, 0))
+        );
+        assert_eq!(
+            board.place(0, 7, Color::Red),
+            Err(BoardError::InvalidCell(0, 7))
+        );
+
+        let mut board = Board::new();
+        board.place(0, 3, Color::Red).unwrap();
+        board.place(1, 3, Color::Yellow).unwrap();
+        assert!(board.is_legal());
+        assert_eq!(board, Board::from_moves("44").unwrap());
+    }
+
+    #[test]
+    fn finds_threat_columns() {
+        let board = Board::from_notation("7/7/7/7/1yyy3/1rrr3").unwrap();
+
+        assert_eq!(board.threat_columns(Color::Red).as_slice(), &[0, 4]);
+        assert_eq!(
+            board.threat_columns(Color::Yellow).as_slice(),
+            &[] as &[usize]
+        );
+    }
+
+    #[test]
+    fn pops_out_bottom_pieces() {
+        let mut board = Board::from_moves("4445").unwrap();
+
+        assert_eq!(board.pop_out(3), Ok(Color::Red));
+        assert_eq!(board, Board::from_notation("7/7/7/7/3r3/3yy2").unwrap());
+        assert_eq!(board.height(3), 2);
+        assert_eq!(board.pop_out(0), Err(BoardError::EmptyFile(0)));
+        assert_eq!(board.pop_out(7), Err(BoardError::InvalidFile(7)));
+    }
+
+    #[test]
+    fn phase_follows_piece_count() {
+        let phase = |pieces: usize| {
+            let moves = "1234567".repeat(6);
+            Board::from_moves(&moves[..pieces]).unwrap().phase()
+        };
+
+        assert_eq!(phase(0), Phase::Opening);
+        assert_eq!(phase(9), Phase::Opening);
+        assert_eq!(phase(10), Phase::Midgame);
+        assert_eq!(phase(27), Phase::Midgame);
+        assert_eq!(phase(28), Phase::Endgame);
+        assert_eq!(phase(42), Phase::Endgame);
+    }
+
+    #[test]
+    fn fills_remaining_cells() {
+        let mut board = Board::from_moves("4455").unwrap();
+        assert!(!board.is_full());
+
+        board.fill_remaining(Color::Yellow);
+
+        assert!(board.is_full());
+        assert_eq!(board.piece_count(), 42);
+        assert!(board.legal_files().is_empty());
+        assert_eq!(board.piece_at(0, 3), Some(Color::Red));
+        assert_eq!(board.piece_at(5, 0), Some(Color::Yellow));
+        assert_eq!(board.pieces().count(), 42);
+
+        board.fill_remaining(Color::Red);
+        assert_eq!(board.piece_at(5, 0), Some(Color::Yellow));
+    }
+
+    #[test]
+    fn iterates_pieces() {
+        let board = Board::from_notation("7/7/7/7/3y3/2rr3").unwrap();
+
+        assert_eq!(
+            board.pieces().collect::<Vec<_>>(),
+            vec![(31, Color::Yellow), (37, Color::Red), (38, Color::Red)]
+        );
+        assert_eq!(Board::new().pieces().count(), 0);
+
+        let mut rng = StdRng::seed_from_u64(392);
+        for _ in 0..100 {
+            let board = Board::random_legal(&mut rng, 42);
+            assert_eq!(board.pieces().count(), board.piece_count());
+        }
+    }
+
+    #[test]
+    fn scores_moves_statically() {
+        let board = Board::from_notation("7/7/7/r6/r5y/r4yy").unwrap();
+
+        let win = board.score_move(0, Color::Red).unwrap();
+        let center = board.score_move(3, Color::Red).unwrap();
+        let edge = board.score_move(6, Color::Red).unwrap();
+        assert!(win > center && center > edge, "{} {} {}", win, center, edge);
+
+        // Dropping in file 4 lets Yellow complete the second row on top of it.
+        let board = Board::from_notation("7/7/7/7/ryyy3/rrry3").unwrap();
+        assert!(
+            board.score_move(6, Color::Red).unwrap() > board.score_move(4, Color::Red).unwrap()
+        );
+
+        let full = Board::from_moves("111111").unwrap();
+        assert_eq!(full.score_move(0, Color::Red), Err(BoardError::FullFile(0)));
+    }
+
+    #[test]
+    fn rejects_scoring_files_off_the_board() {
+        let board = Board::new();
+        assert_eq!(
+            board.score_move(7, Color::Red),
+            Err(BoardError::InvalidFile(7))
+        );
+        assert_eq!(
+            board.score_move(usize::MAX, Color::Yellow),
+            Err(BoardError::InvalidFile(usize::MAX))
+        );
+    }
+
+    #[test]
+    fn finds_unblocked_threats() {
+        // Red threatens to complete the bottom row in file 3.
+        let board = Board::from_moves("15253").unwrap();
+
+        assert_eq!(board.unblocked_threat(6, Color::Yellow), Some(3));
+        assert_eq!(board.unblocked_threat(3, Color::Yellow), None);
+        assert_eq!(board.unblocked_threat(6, Color::Red), None);
+        assert_eq!(Board::new().unblocked_threat(0, Color::Red), None);
+    }
+
+    #[test]
+    fn finds_full_columns() {
+        let board = Board::from_moves("111111333333").unwrap();
+
+        assert_eq!(board.full_columns().as_slice(), &[0, 2]);
+        assert_eq!(board.legal_files().as_slice(), &[1, 3, 4, 5, 6]);
+        assert!(Board::new().full_columns().is_empty());
+    }
+
+    #[test]
+    fn successor_evaluations_cover_legal_files() {
+        let board = Board::from_moves("111111333333").unwrap();
+        let evaluations = board.successor_evaluations(Color::Red, 3);
+
+        let files = evaluations
+            .iter()
+            .map(|&(file, _)| file)
+            .collect::<Vec<_>>();
+        assert_eq!(files, board.legal_files().to_vec());
+
+        let analysis = Minimax::new(&board, Color::Red, 3).analyze();
+        for (file, eval) in evaluations {
+            assert!(analysis.contains(&(file, eval)));
+        }
+    }
+
+    #[test]
+    fn safe_legal_files_prune_blunders() {
+        // Red can win on the bottom row.
+        let board = Board::from_moves("15253").unwrap();
+        assert_eq!(board.safe_legal_files(Color::Red).as_slice(), &[3]);
+
+        // Yellow must block it.
+        assert_eq!(board.safe_legal_files(Color::Yellow).as_slice(), &[3]);
+
+        // Red threatens both ends of the bottom row, so every Yellow move loses.
+        let board = Board::from_notation("7/7/7/7/1yy4/1rrr3").unwrap();
+        assert_eq!(board.safe_legal_files(Color::Yellow), board.legal_files());
+
+        // Playing under Yellow's threat is the only blunder here.
+        let board = Board::from_notation("7/7/7/7/ryyy3/rrry3").unwrap();
+        assert_eq!(
+            board.safe_legal_files(Color::Red).as_slice(),
+            &[0, 1, 2, 3, 5, 6]
+        );
+    }
+
+    #[test]
+    fn notation_errors_name_the_bad_row() {
+        let error = |notation: &str| Board::from_notation(notation).unwrap_err().to_string();
+
+        assert_eq!(
+            error("7/7/7/4rrrr/7/7"),
+            "row 4 of notation has 8 cells, too many; expected 7: 7/7/7/4rrrr/7/7"
+        );
+        assert_eq!(
+            error("7/7/7/7/7/3r2"),
+            "row 6 of notation has 6 cells, too few; expected 7: 7/7/7/7/7/3r2"
+        );
+        assert_eq!(
+            error("7/7/7/7/3r3"),
+            "expected 6 rows in notation, got 5: 7/7/7/7/3r3"
+        );
+        assert_eq!(
+            error("7/7/7/7/7/7/7"),
+            "expected 6 rows in notation, got 7: 7/7/7/7/7/7/7"
+        );
+        assert_eq!(error("7/7/7/7/7/3x3"), "invalid character in notation: x");
+        assert!(Board::from_notation("7/7/7/7/7/9").is_err());
+    }
+
+    #[test]
+    fn legal_files_are_in_ascending_order() {
+        let board = Board::from_moves("111111333333555555777777").unwrap();
+
+        assert_eq!(board.legal_files().as_slice(), &[1, 3, 5]);
+        assert_eq!(
+            Board::new().legal_files().as_slice(),
+            &[0, 1, 2, 3, 4, 5, 6]
+        );
+    }
+
+    #[test]
+    fn inserts_into_empty_board() {
+        let mut board = Board::new();
+
+        board.insert(2, Color::Yellow);
+        assert_eq!(board.yellow, 0x0001FC2000000000);
+
+        board.insert(2, Color::Red);
+        assert_eq!(board.red, 0x0001FC0040000000);
+
+        println!("{}", board);
+    }
+
+    #[test]
+    fn inserts_into_first_last_files() {
+        let mut board = Board::new();
 
         board.insert(6, Color::Yellow);
         assert_eq!(board.yellow, 0x0001FE0000000000);
@@ -305,6 +2407,683 @@ mod test {
         println!("{}", board);
     }
 
+    #[test]
+    fn round_trips_notation() {
+        for notation in ["7/7/7/7/7/7", "7/7/6y/r5y/r5y/r5y", "r6/yr5/1yr4/2yr3/7/7"] {
+            assert_eq!(
+                Board::from_notation(notation).unwrap().to_notation(),
+                notation
+            );
+        }
+    }
+
+    #[test]
+    fn mirrors_and_flips_colors() {
+        let board = Board::from_notation("7/7/7/7/3y3/rr1yr1y").unwrap();
+
+        assert_eq!(
+            board.mirror_horizontal(),
+            Board::from_notation("7/7/7/7/3y3/y1ry1rr").unwrap()
+        );
+        assert_eq!(board.mirror_horizontal().mirror_horizontal(), board);
+        assert_eq!(
+            board.flip_colors(),
+            Board::from_notation("7/7/7/7/3r3/yy1ry1r").unwrap()
+        );
+    }
+
+    #[test]
+    fn recognizes_mirrors() {
+        let board = Board::from_moves("1123").unwrap();
+        let mirrored = Board::from_moves("7765").unwrap();
+
+        assert!(board.is_mirror_of(&mirrored));
+        assert!(mirrored.is_mirror_of(&board));
+        assert!(!board.is_mirror_of(&board));
+        assert!(!board.is_mirror_of(&Board::from_moves("7756").unwrap()));
+
+        let symmetric = Board::from_moves("246").unwrap();
+        assert!(symmetric.is_mirror_of(&symmetric));
+        assert!(Board::new().is_mirror_of(&Board::new()));
+    }
+
+    #[test]
+    fn mirror_horizontal_reflects_every_file() {
+        let mut rng = StdRng::seed_from_u64(364);
+
+        for _ in 0..200 {
+            let board = Board::random_legal(&mut rng, 42);
+            let mirrored = board.mirror_horizontal();
+
+            assert_eq!(mirrored.mirror_horizontal(), board);
+            assert!(mirrored.is_legal());
+            for file in 0..7 {
+                assert!(board.column(file).eq(mirrored.column(6 - file)));
+            }
+            for color in [Color::Red, Color::Yellow] {
+                assert_eq!(board.has_connect_4(color), mirrored.has_connect_4(color));
+            }
+            assert_eq!(
+                board
+                    .legal_files()
+                    .iter()
+                    .map(|f| 6 - f)
+                    .rev()
+                    .collect::<Vec<_>>(),
+                mirrored.legal_files().to_vec()
+            );
+        }
+    }
+
+    #[test]
+    fn vertical_flip_is_not_a_symmetry() {
+        // Reversing the rows of the notation turns the board upside down.
+        let flip_vertical = |board: &Board| {
+            let notation = board.to_notation();
+            Board::from_notation(&notation.split('/').rev().collect::<Vec<_>>().join("/")).unwrap()
+        };
+
+        let board = Board::from_notation("7/7/7/7/3y3/2rr3").unwrap();
+        assert!(board.is_legal());
+        assert!(!flip_vertical(&board).is_legal());
+
+        // Only boards whose files are all empty or full survive the flip.
+        let mut rng = StdRng::seed_from_u64(364);
+        for _ in 0..200 {
+            let board = Board::random_legal(&mut rng, 42);
+            let settled = (0..7).all(|file| matches!(board.height(file), 0 | 6));
+            assert!(board.is_legal());
+            assert_eq!(flip_vertical(&board).is_legal(), settled);
+        }
+    }
+
+    #[test]
+    fn normalizes_mirrored_and_flipped_positions_identically() {
+        let board = Board::from_moves("3345").unwrap();
+        let counterpart = board.mirror_horizontal().flip_colors();
+
+        assert_eq!(
+            board.normalize(Color::Red),
+            counterpart.normalize(Color::Yellow)
+        );
+        assert_eq!(
+            board.normalize(Color::Red),
+            board.mirror_horizontal().normalize(Color::Red)
+        );
+        assert_ne!(board.normalize(Color::Red), board.normalize(Color::Yellow));
+    }
+
+    #[test]
+    fn renders_flipped_board() {
+        let board = Board::from_notation("7/7/7/7/3y3/rr1yr1y").unwrap();
+        let rotated = Board::from_notation("y1ry1rr/3y3/7/7/7/7").unwrap();
+
+        assert_eq!(board.render(false), board.to_string());
+        assert_eq!(board.render(true), rotated.to_string());
+        assert_ne!(board.render(true), board.to_string());
+    }
+
+    #[test]
+    fn renders_plain_board() {
+        let board = Board::from_notation("7/7/7/7/3y3/rr1yr1y").unwrap();
+
+        assert_eq!(
+            board.to_string_plain(),
+            "  1  2  3  4  5  6  7   \n\
+             | .  .  .  .  .  .  . |\n\
+             | .  .  .  .  .  .  . |\n\
+             | .  .  .  .  .  .  . |\n\
+             | .  .  .  .  .  .  . |\n\
+             | .  .  .  Y  .  .  . |\n\
+             | R  R  .  Y  R  .  Y |\n"
+        );
+    }
+
+    #[test]
+    fn parses_ascii_diagrams() {
+        let mut rng = StdRng::seed_from_u64(423);
+        for _ in 0..200 {
+            let board = Board::random_legal(&mut rng, 42);
+            assert_eq!(Board::from_ascii(&board.to_string_plain()), Ok(board));
+
+            let mask = board.winning_line_mask(Color::Red);
+            let highlighted = board.render_highlighting(mask, false, true);
+            assert_eq!(Board::from_ascii(&highlighted), Ok(board));
+        }
+
+        let pasted = "
+            A B C D E F G
+            .......
+            .......
+            .......
+            .......
+            ...y...
+            rr.yr.y
+        ";
+        assert_eq!(
+            Board::from_ascii(pasted),
+            Ok(Board::from_notation("7/7/7/7/3y3/rr1yr1y").unwrap())
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_ascii_diagrams() {
+        let ragged = "|.......|\n|.......|\n|.......|\n|.......|\n|...Y..|\n|RR.YR.Y|";
+        assert!(matches!(
+            Board::from_ascii(ragged),
+            Err(BoardError::InvalidAscii(_))
+        ));
+
+        let too_few = ".......\n.......\n.......\n...Y...\nRR.YR.Y";
+        assert!(matches!(
+            Board::from_ascii(too_few),
+            Err(BoardError::InvalidAscii(_))
+        ));
+
+        let unknown = ".......\n.......\n.......\n.......\n...X...\nRR.YR.Y";
+        assert!(matches!(
+            Board::from_ascii(unknown),
+            Err(BoardError::InvalidAscii(_))
+        ));
+
+        let floating = ".......\n.......\n.......\n...Y...\n.......\nRR.YR..";
+        assert_eq!(
+            Board::from_ascii(floating),
+            Err(BoardError::Floating(1 << 24))
+        );
+    }
+
+    #[test]
+    fn renders_differences_between_boards() {
+        let board = Board::from_notation("7/7/7/7/3y3/rr1yr1y").unwrap();
+        let mut next = board;
+        next.insert(3, Color::Red);
+
+        assert_eq!(board.render_diff(&board), board.to_string_plain());
+        assert_eq!(
+            board.render_diff(&next),
+            "  1  2  3  4  5  6  7   \n\
+             | .  .  .  .  .  .  . |\n\
+             | .  .  .  .  .  .  . |\n\
+             | .  .  .  .  .  .  . |\n\
+             | .  .  .  *  .  .  . |\n\
+             | .  .  .  Y  .  .  . |\n\
+             | R  R  .  Y  R  .  Y |\n"
+        );
+        assert_eq!(next.render_diff(&board).matches('*').count(), 1);
+    }
+
+    #[test]
+    fn plain_render_has_no_escape_codes() {
+        let mut rng = StdRng::seed_from_u64(356);
+        for _ in 0..200 {
+            let board = Board::random_legal(&mut rng, 42);
+
+            assert!(!board.to_string_plain().contains('\x1b'));
+            assert!(!board.render_plain(true).contains('\x1b'));
+        }
+    }
+
+    #[test]
+    fn iterates_column_bottom_up() {
+        let board = Board::from_notation("7/7/2y4/2y4/2r4/2y4").unwrap();
+
+        let column: Vec<Color> = board.column(2).collect();
+        assert_eq!(
+            column,
+            [Color::Yellow, Color::Red, Color::Yellow, Color::Yellow]
+        );
+        assert_eq!(board.height(2), 4);
+
+        assert_eq!(board.column(0).count(), 0);
+        assert_eq!(board.height(0), 0);
+    }
+
+    #[test]
+    fn round_trips_compact_encoding() {
+        let mut rng = StdRng::seed_from_u64(345);
+        for _ in 0..500 {
+            let board = Board::random_legal(&mut rng, 42);
+
+            let code = board.to_compact();
+            assert!(code < 1 << 49);
+            assert_eq!(Board::from_compact(code), Ok(board));
+        }
+    }
+
+    #[test]
+    fn compact_encoding_is_canonical() {
+        let a = Board::from_moves("1324").unwrap();
+        let b = Board::from_moves("2413").unwrap();
+
+        assert_eq!(a.to_compact(), b.to_compact());
+        assert_ne!(
+            a.to_compact(),
+            Board::from_moves("3124").unwrap().to_compact()
+        );
+        assert_eq!(Board::new().to_compact(), FILE[0]);
+    }
+
+    #[test]
+    fn rejects_invalid_compact_code() {
+        assert!(Board::from_compact(0).is_err());
+        assert!(Board::from_compact(1 << 49).is_err());
+    }
+
+    #[test]
+    fn builds_from_move_sequence() {
+        let board = Board::from_moves("4435").unwrap();
+        assert_eq!(board, Board::from_notation("7/7/7/7/3y3/2rry2").unwrap());
+
+        assert!(Board::from_moves("1111111").is_err());
+        assert!(Board::from_moves("48").is_err());
+    }
+
+    #[test]
+    fn previews_landing_cell() {
+        let board = Board::from_notation("2r4/2y4/2r4/2y4/2r4/1ry4").unwrap();
+
+        let preview = board.with_preview(1, Color::Yellow);
+        assert_eq!(preview.board, board);
+        assert_eq!(preview.color, Color::Yellow);
+        assert_eq!(preview.cell, Some(29));
+
+        assert_eq!(board.with_preview(0, Color::Red).cell, Some(35));
+        assert_eq!(board.with_preview(2, Color::Red).cell, None);
+    }
+
+    #[test]
+    fn finds_threats_in_every_arrangement() {
+        let board = Board::from_notation("7/7/7/7/7/rr1r3").unwrap();
+        assert_eq!(board.threats(Color::Red), 1 << 37);
+
+        let board = Board::from_notation("7/7/7/3r3/2r4/1r5").unwrap();
+        assert_eq!(board.threats(Color::Red), 1 << 18);
+        assert_eq!(board.threats(Color::Yellow), 0);
+    }
+
+    #[test]
+    fn counts_open_threes() {
+        let open = Board::from_notation("7/7/7/7/7/rrr4").unwrap();
+        assert_eq!(open.open_threes(Color::Red), 1);
+
+        let split = Board::from_notation("7/7/7/7/7/r1rr1yy").unwrap();
+        assert_eq!(split.open_threes(Color::Red), 1);
+
+        let blocked = Board::from_notation("7/7/7/7/7/rrry3").unwrap();
+        assert_eq!(blocked.open_threes(Color::Red), 0);
+
+        // The fourth cell is empty but can't be played yet.
+        let unsupported = Board::from_notation("7/7/7/7/rrr4/yyr4").unwrap();
+        assert_eq!(unsupported.open_threes(Color::Red), 0);
+        assert!(open.evaluate() > blocked.evaluate());
+    }
+
+    #[test]
+    fn rewards_threats_on_favourable_rows() {
+        // Red's threat on the third row from the bottom will fall to Red under claimeven.
+        let odd = Board::from_notation("7/7/7/rrr4/yyr4/yry4").unwrap();
+        assert_eq!(odd.evaluate_breakdown(Color::Yellow).parity, 1);
+
+        // The same threat on the second row will be claimed by Yellow instead.
+        let even = Board::from_notation("7/7/7/7/rrr4/yyry3").unwrap();
+        assert_eq!(even.evaluate_breakdown(Color::Yellow).parity, 0);
+    }
+
+    #[test]
+    fn parity_follows_whoever_opened() {
+        // The same threats as above with the colors swapped, in a game Yellow opened: Yellow now
+        // claims the odd rows.
+        let odd = Board::from_notation("7/7/7/yyy4/rry4/ryr4").unwrap();
+        assert_eq!(odd.evaluate_breakdown(Color::Red).parity, -1);
+        let even = Board::from_notation("7/7/7/7/yyy4/rryr3").unwrap();
+        assert_eq!(even.evaluate_breakdown(Color::Red).parity, 0);
+
+        // Red's threat on the second row only counts once Yellow opened.
+        let red = Board::from_notation("7/7/7/7/rrr4/yyyr2y").unwrap();
+        assert_eq!(red.evaluate_breakdown(Color::Red).parity, 0);
+        assert_eq!(red.evaluate_breakdown(Color::Yellow).parity, 1);
+    }
+
+    #[test]
+    fn infers_single_move() {
+        let board = Board::from_moves("445").unwrap();
+        let next = Board::from_moves("4453").unwrap();
+
+        assert_eq!(board.infer_move(&next), Some((2, Color::Yellow)));
+        assert_eq!(board.infer_move(&board), None);
+    }
+
+    #[test]
+    fn rejects_invalid_move_diffs() {
+        let board = Board::from_moves("445").unwrap();
+
+        // Two pieces added
+        assert_eq!(board.infer_move(&Board::from_moves("44536").unwrap()), None);
+        // A piece removed
+        assert_eq!(Board::from_moves("4453").unwrap().infer_move(&board), None);
+        // A floating piece
+        let floating = Board::from_notation("7/7/7/1y5/3y3/3rr2").unwrap();
+        assert_eq!(board.infer_move(&floating), None);
+        // A piece changed color
+        let swapped = Board::from_notation("7/7/7/7/3r3/3ry2").unwrap();
+        assert_eq!(board.infer_move(&swapped), None);
+    }
+
+    #[test]
+    fn removes_from_mixed_column() {
+        let mut board = Board::from_notation("7/7/7/2r4/2y4/2r4").unwrap();
+
+        assert_eq!(board.remove(2), Ok(Color::Red));
+        assert_eq!(board.remove(2), Ok(Color::Yellow));
+        assert_eq!(board.remove(2), Ok(Color::Red));
+        assert_eq!(board, Board::new());
+    }
+
+    #[test]
+    fn try_move_reports_the_outcome() {
+        let mut board = Board::from_moves("44").unwrap();
+        assert_eq!(
+            board.try_move(3, Color::Red),
+            Ok(MoveResult {
+                row: 2,
+                file: 3,
+                won: false,
+                full: false,
+            })
+        );
+        assert_eq!(board.piece_at(2, 3), Some(Color::Red));
+
+        let mut board = Board::from_notation("7/7/7/7/yyy4/rrr4").unwrap();
+        let result = board.try_move(3, Color::Red).unwrap();
+        assert!(result.won && !result.full);
+        assert_eq!(result.row, 0);
+
+        let mut board =
+            Board::from_notation("ryryryr/ryryryr/yryryry/yryryry/ryryryr/ryryryr").unwrap();
+        assert_eq!(board.try_move(0, Color::Red), Err(BoardError::FullFile(0)));
+        assert_eq!(
+            board.try_move(7, Color::Red),
+            Err(BoardError::InvalidFile(7))
+        );
+
+        let _ = board.remove(0);
+        let result = board.try_move(0, Color::Red).unwrap();
+        assert!(result.full && !result.won);
+        assert_eq!(result.row, 5);
+    }
+
+    #[test]
+    fn remove_errors_on_empty_or_invalid_file() {
+        let mut board = Board::from_notation("7/7/7/7/7/r6").unwrap();
+
+        assert_eq!(board.remove(1), Err(BoardError::EmptyFile(1)));
+        assert_eq!(board.remove(7), Err(BoardError::InvalidFile(7)));
+        assert_eq!(board, Board::from_notation("7/7/7/7/7/r6").unwrap());
+    }
+
+    #[test]
+    fn finds_immediate_win() {
+        let board = Board::from_notation("7/7/7/7/3yy2/2rrr2").unwrap();
+        assert_eq!(board.find_immediate_win(Color::Red), Some(1));
+
+        let board = Board::from_notation("7/7/7/y6/r6/rrry3").unwrap();
+        assert_eq!(board.find_immediate_win(Color::Red), None);
+        assert_eq!(board.find_immediate_win(Color::Yellow), None);
+    }
+
+    #[test]
+    fn finds_immediate_wins_for_both_colors() {
+        let board = Board::from_notation("7/7/7/r5y/r5y/r5y").unwrap();
+        assert_eq!(board.immediate_win_moves(), (Some(0), Some(6)));
+
+        let board = Board::from_notation("7/7/7/7/3yy2/2rrr2").unwrap();
+        assert_eq!(board.immediate_win_moves(), (Some(1), None));
+        assert_eq!(Board::new().immediate_win_moves(), (None, None));
+    }
+
+    #[test]
+    fn ignores_unsupported_immediate_win() {
+        let board = Board::from_notation("7/7/7/yyy4/rrr4/yry4").unwrap();
+        assert_eq!(board.find_immediate_win(Color::Yellow), None);
+        assert_eq!(board.find_immediate_win(Color::Red), None);
+    }
+
+    #[test]
+    fn finds_forcing_sequence() {
+        // Red threatens g1 with e1, and after the block e2 threatens both c2 and g2.
+        let board = Board::from_notation("7/7/7/3y3/3r1r1/yyyr1r1").unwrap();
+
+        assert!(!board.has_forcing_sequence(Color::Red, 0));
+        assert!(!board.has_forcing_sequence(Color::Red, 1));
+        assert!(board.has_forcing_sequence(Color::Red, 2));
+        assert!(!board.has_forcing_sequence(Color::Yellow, 2));
+    }
+
+    #[test]
+    fn detects_dead_draw() {
+        let board =
+            Board::from_notation("r1ry1ry/yryyyry/yyyrrry/rrryyyr/ryyryyy/yyyryry").unwrap();
+
+        assert!(!board.has_connect_4(Color::Red));
+        assert!(!board.has_connect_4(Color::Yellow));
+        assert!(board.no_winning_chances());
+    }
+
+    #[test]
+    fn open_board_has_winning_chances() {
+        assert!(!Board::new().no_winning_chances());
+
+        // With the whole top row empty, either side can still fill a line in it.
+        let board = Board::from_notation("7/yryyyry/yyyrrry/rrryyyr/ryyryyy/yyyryry").unwrap();
+        assert!(!board.no_winning_chances());
+    }
+
+    #[test]
+    fn breaks_down_connect_4() {
+        let breakdown = Board::from_notation("7/7/7/7/7/rrrr3")
+            .unwrap()
+            .evaluate_breakdown(Color::Red);
+
+        assert_eq!(breakdown.red_potential, [1, 0, 0, 0]);
+        assert_eq!(breakdown.red_connect_4, [1, 0, 0, 0]);
+        assert_eq!(breakdown.yellow_potential, [0; 4]);
+        assert_eq!(breakdown.yellow_connect_4, [0; 4]);
+        assert_eq!(breakdown.red_open_threes, 1);
+        assert_eq!(breakdown.parity, 1);
+        assert_eq!(breakdown.center, 1);
+        assert_eq!(breakdown.score, 1 + 42 + 4 + 1 + 1);
+    }
+
+    #[test]
+    fn breaks_down_potential_lines() {
+        let breakdown = Board::from_notation("7/7/7/7/y2y3/r2r3")
+            .unwrap()
+            .evaluate_breakdown(Color::Red);
+
+        assert_eq!(breakdown.red_potential, [1, 0, 0, 0]);
+        assert_eq!(breakdown.yellow_potential, [1, 0, 0, 0]);
+        assert_eq!(breakdown.red_connect_4, [0; 4]);
+        assert_eq!(breakdown.yellow_connect_4, [0; 4]);
+        assert_eq!(breakdown.score, 0);
+
+        // Only the ends of a line are counted, through empty cells in between
+        let breakdown = Board::from_notation("7/7/3r3/7/7/r6")
+            .unwrap()
+            .evaluate_breakdown(Color::Red);
+
+        assert_eq!(breakdown.red_potential, [0, 0, 1, 0]);
+        assert_eq!(breakdown.score, 1);
+    }
+
+    #[test]
+    fn counts_potential_lines() {
+        // Red's two bottom pieces and the diagonal pair each span a line through empty cells;
+        // Yellow's pair is only one.
+        let board = Board::from_notation("7/7/3r3/7/y2y3/r2r3").unwrap();
+        assert_eq!(board.potential_lines(Color::Red), 2);
+        assert_eq!(board.potential_lines(Color::Yellow), 1);
+
+        let breakdown = board.evaluate_breakdown(Color::Yellow);
+        assert_eq!(
+            board.potential_lines(Color::Red),
+            breakdown.red_potential.iter().sum::<u32>()
+        );
+        assert_eq!(Board::new().potential_lines(Color::Red), 0);
+    }
+
+    #[test]
+    fn weights_scale_each_term() {
+        let board = Board::from_notation("7/7/7/7/7/rrrr3").unwrap();
+        let weights = EvalWeights {
+            potential: 2,
+            connect_4: 10,
+            open_three: 5,
+            parity: 3,
+            center: 7,
+            reach: None,
+        };
+
+        assert_eq!(
+            board.evaluate_with(&EvalWeights::default(), Color::Red),
+            board.evaluate()
+        );
+        assert_eq!(
+            board.evaluate_with(&weights, Color::Red),
+            2 + 10 + 5 + 3 + 7
+        );
+    }
+
+    #[test]
+    fn finds_reachable_empty_cells() {
+        // Heights 0, 0, 0, 3, 2, 2, 0.
+        let board = Board::from_moves("4445566").unwrap();
+        let cells = |cells: &[(usize, usize)]| {
+            cells.iter().fold(0, |mask, &(row, file)| {
+                mask | 1u64 << ((5 - row) * 7 + file)
+            })
+        };
+        let next = cells(&[(0, 0), (0, 1), (0, 2), (3, 3), (2, 4), (2, 5), (0, 6)]);
+        let second = cells(&[(1, 0), (1, 1), (1, 2), (4, 3), (3, 4), (3, 5), (1, 6)]);
+
+        assert_eq!(board.reachable_empty(1), next);
+        assert_eq!(board.reachable_empty(1), board.next_cells());
+        assert_eq!(board.reachable_empty(2), next | second);
+        assert_eq!(board.reachable_empty(6), board.empty() & GAME_MASK);
+    }
+
+    #[test]
+    fn reach_drops_potential_through_distant_cells() {
+        // Late in the game, with most of the room left high in files 1 and 2.
+        let board = Board::from_notation("y3y1y/r2rryr/r2yyry/y1yrryr/r1ryrry/yrrryyy").unwrap();
+        let naive = EvalWeights::default();
+
+        assert_eq!(board.evaluate_with(&naive, Color::Red), 2);
+        assert_eq!(
+            board.evaluate_with(
+                &EvalWeights {
+                    reach: Some(1),
+                    ..naive
+                },
+                Color::Red
+            ),
+            -1
+        );
+        assert_eq!(
+            board.evaluate_with(
+                &EvalWeights {
+                    reach: Some(6),
+                    ..naive
+                },
+                Color::Red
+            ),
+            2
+        );
+    }
+
+    #[test]
+    fn center_stack_outscores_corner() {
+        let center = Board::from_notation("7/7/7/7/7/3r3").unwrap();
+        let corner = Board::from_notation("7/7/7/7/7/r6").unwrap();
+
+        assert!(center.evaluate() > corner.evaluate());
+        assert_eq!(center.evaluate_breakdown(Color::Yellow).center, 1);
+        assert_eq!(corner.evaluate_breakdown(Color::Yellow).center, 0);
+
+        let no_center = EvalWeights {
+            center: 0,
+            ..EvalWeights::default()
+        };
+        assert_eq!(
+            center.evaluate() - center.evaluate_with(&no_center, Color::Yellow),
+            EvalWeights::default().center
+        );
+    }
+
+    #[test]
+    fn evaluation_is_antisymmetric_between_colors() {
+        let mut rng = StdRng::seed_from_u64(351);
+        for _ in 0..2000 {
+            let board = Board::random_legal(&mut rng, 42);
+            let flipped = board.flip_colors();
+
+            // Parity goes to whoever opened, so it flips along with the side to move.
+            let weights = EvalWeights::default();
+            assert_eq!(
+                board.evaluate_with(&weights, Color::Red),
+                -flipped.evaluate_with(&weights, Color::Yellow),
+                "{}",
+                board.to_notation()
+            );
+        }
+    }
+
+    #[test]
+    fn evaluation_is_symmetric_under_mirror() {
+        let mut rng = StdRng::seed_from_u64(3510);
+        for _ in 0..2000 {
+            let board = Board::random_legal(&mut rng, 42);
+
+            assert_eq!(
+                board.evaluate(),
+                board.mirror_horizontal().evaluate(),
+                "{}",
+                board.to_notation()
+            );
+        }
+    }
+
+    #[test]
+    fn detects_last_move_win() {
+        let board = Board::from_notation("7/7/3r3/2ry3/1ryy3/ryyr3").unwrap();
+        assert!(board.last_move_wins(3));
+        assert!(board.last_move_wins(0));
+        assert!(board.last_move_wins(2));
+        assert!(!board.last_move_wins(5));
+
+        let board = Board::from_notation("7/7/7/7/7/rrr1rrr").unwrap();
+        assert!(!board.last_move_wins(2));
+    }
+
+    #[test]
+    fn random_playout_returns_actual_winner() {
+        let mut rng = StdRng::seed_from_u64(354);
+        for _ in 0..500 {
+            let mut board = Board::new();
+            match board.random_playout(Color::Red, &mut rng) {
+                Some(winner) => {
+                    assert!(board.has_connect_4(winner));
+                    assert!(!board.has_connect_4(winner.other()));
+                }
+                None => {
+                    assert!(board.legal_files().is_empty());
+                    assert!(!board.has_connect_4(Color::Red));
+                    assert!(!board.has_connect_4(Color::Yellow));
+                }
+            }
+        }
+    }
+
     #[test]
     fn finds_horizontal_connect_4() {
         let board = Board::from_notation("7/7/7/7/2yyy2/2rrrr1").unwrap();
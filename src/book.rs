@@ -0,0 +1,195 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs::File,
+    io::{BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use anyhow::bail;
+
+use crate::{
+    board::{Board, Color, PositionKey},
+    minimax::Minimax,
+};
+
+/// A `save`d entry: an 8-byte key and a 1-byte file.
+const RECORD_BYTES: usize = 9;
+
+/// Known best moves for opening positions, keyed by `PositionKey`. A position's mirror image
+/// shares its entry, with the move reflected.
+#[derive(Debug, Clone, Default)]
+pub struct Book {
+    moves: HashMap<PositionKey, usize>,
+}
+
+impl Book {
+    /// The opening theory everyone agrees on: Red wins only by starting in the center, and
+    /// Yellow's most resilient reply is to stack on it.
+    pub fn standard() -> Self {
+        let mut book = Book::default();
+        book.insert(&Board::new(), 3);
+        book.insert(&Board::from_moves("4").expect("valid moves"), 3);
+        book
+    }
+
+    /// Builds a book offline, searching every distinct position with fewer than `plies` pieces
+    /// `depth` plies deep. Mirror images are searched once and share an entry. Finished games
+    /// are left out.
+    pub fn generate(plies: usize, depth: usize) -> Self {
+        let mut book = Book::default();
+        let mut seen = HashSet::new();
+        book.generate_from(&mut Board::new(), Color::Red, plies, depth, &mut seen);
+        book
+    }
+
+    fn generate_from(
+        &mut self,
+        board: &mut Board,
+        color: Color,
+        plies: usize,
+        depth: usize,
+        seen: &mut HashSet<PositionKey>,
+    ) {
+        if plies == 0 || board.legal_files().is_empty() {
+            return;
+        }
+
+        let mirrored = board.mirror_horizontal();
+        let (key, mirrored_key) = (PositionKey::from(&*board), PositionKey::from(&mirrored));
+        if !seen.insert(key.min(mirrored_key)) {
+            return;
+        }
+
+        let file = Minimax::new(board, color, depth).best_move();
+        match key <= mirrored_key {
+            true => self.insert(board, file),
+            false => self.insert(&mirrored, Board::mirror_file(file)),
+        }
+
+        for file in board.legal_files() {
+            board.insert(file, color);
+            if !board.last_move_wins(file) {
+                self.generate_from(board, color.other(), plies - 1, depth, seen);
+            }
+            let _ = board.remove(file);
+        }
+    }
+
+    /// Saves the book in a compact binary format: one 9-byte record per entry, the
+    /// little-endian `PositionKey` followed by the file, sorted so regenerating an unchanged book
+    /// gives an identical file.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), anyhow::Error> {
+        let mut entries = self.moves.iter().collect::<Vec<_>>();
+        entries.sort();
+
+        let mut out = BufWriter::new(File::create(path)?);
+        for (&key, &file) in entries {
+            out.write_all(&u64::from(key).to_le_bytes())?;
+            out.write_all(&[file as u8])?;
+        }
+        out.flush()?;
+
+        Ok(())
+    }
+
+    /// Loads a book written by `save`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, anyhow::Error> {
+        let mut bytes = Vec::new();
+        BufReader::new(File::open(path)?).read_to_end(&mut bytes)?;
+        if bytes.len() % RECORD_BYTES != 0 {
+            bail!(
+                "book file has {} bytes, not a whole number of records",
+                bytes.len()
+            );
+        }
+
+        let mut book = Book::default();
+        for record in bytes.chunks_exact(RECORD_BYTES) {
+            let (key, file) = record.split_at(8);
+            let key = u64::from_le_bytes(key.try_into().expect("8 bytes"));
+            let board = Board::from_compact(key)?;
+            match file[0] as usize {
+                file @ 0..7 => book.insert(&board, file),
+                file => bail!("book move {} is not a file", file),
+            }
+        }
+
+        Ok(book)
+    }
+
+    pub fn insert(&mut self, board: &Board, file: usize) {
+        self.moves.insert(PositionKey::from(board), file);
+    }
+
+    pub fn lookup(&self, board: &Board) -> Option<usize> {
+        self.moves
+            .get(&PositionKey::from(board))
+            .copied()
+            .or_else(|| {
+                self.moves
+                    .get(&PositionKey::from(&board.mirror_horizontal()))
+                    .map(|&file| Board::mirror_file(file))
+            })
+    }
+
+    pub fn len(&self) -> usize {
+        self.moves.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.moves.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn looks_up_mirrored_positions() {
+        let mut book = Book::default();
+        book.insert(&Board::from_moves("12").unwrap(), 2);
+
+        assert_eq!(book.lookup(&Board::from_moves("12").unwrap()), Some(2));
+        assert_eq!(book.lookup(&Board::from_moves("76").unwrap()), Some(4));
+        assert_eq!(book.lookup(&Board::from_moves("13").unwrap()), None);
+        assert_eq!(Book::standard().lookup(&Board::new()), Some(3));
+    }
+
+    #[test]
+    fn generates_mirror_consistent_book() {
+        let book = Book::generate(2, 4);
+
+        // The empty board and one of each mirrored pair of first moves.
+        assert_eq!(book.len(), 5);
+        // The center drop is its own mirror image, so it has nothing to be consistent with.
+        for file in [0, 1, 2, 4, 5, 6] {
+            let board = Board::from_moves(&(file + 1).to_string()).unwrap();
+            let best = book.lookup(&board).unwrap();
+            assert_eq!(book.lookup(&board.mirror_horizontal()), Some(6 - best));
+        }
+        assert_eq!(book.lookup(&Board::from_moves("44").unwrap()), None);
+    }
+
+    #[test]
+    fn saves_and_loads_binary_books() {
+        let path = std::env::temp_dir().join(format!("connect-4-book-{}.bin", std::process::id()));
+        let book = Book::generate(3, 2);
+
+        book.save(&path).unwrap();
+        assert_eq!(
+            std::fs::metadata(&path).unwrap().len() as usize,
+            book.len() * RECORD_BYTES
+        );
+        let loaded = Book::load(&path).unwrap();
+        assert_eq!(loaded.moves, book.moves);
+        for moves in ["", "1", "4", "7", "12", "76", "44"] {
+            let board = Board::from_moves(moves).unwrap();
+            assert_eq!(loaded.lookup(&board), book.lookup(&board), "{}", moves);
+        }
+
+        std::fs::write(&path, [0; 10]).unwrap();
+        assert!(Book::load(&path).is_err());
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,224 @@
+use anyhow::{anyhow, bail};
+
+use crate::{board::Board, minimax::Minimax, repl::Repl};
+
+pub const USAGE: &str = "\
+Usage: connect-4 [options]
+       connect-4 solve <notation> [options]
+
+Options:
+  --depth <plies>           How deep the engine searches (default 12)
+  --connect-n <3-5>         Play Connect-N instead of Connect-4
+  --variant <standard|pop-out>
+  --plain                   No color or Unicode, for screen readers and logs
+  --seed <n>                Makes the first move toss and Mcts rollouts reproducible
+  --hotseat                 Two people play each other, with no computer moves
+  --adaptive                Search as deep as about a second allows, instead of to --depth
+  --protocol                Speak the engine line protocol on stdin/stdout
+  --generate-book <plies> <path>
+                            Write an opening book and exit
+  --help";
+
+/// What the binary was asked to do.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    /// Play interactively in the REPL.
+    Play,
+    Protocol,
+    /// Print the solver's score and best move for a position, then exit.
+    Solve(String),
+    GenerateBook {
+        plies: usize,
+        path: String,
+    },
+    Help,
+}
+
+/// Command line options, parsed by hand to stay within the crate's few dependencies.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    pub command: Command,
+    pub depth: Option<usize>,
+    pub connect: usize,
+    pub pop_out: bool,
+    pub plain: bool,
+    pub seed: Option<u64>,
+    pub hotseat: bool,
+    pub adaptive: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            command: Command::Play,
+            depth: None,
+            connect: 4,
+            pop_out: false,
+            plain: false,
+            seed: None,
+            hotseat: false,
+            adaptive: false,
+        }
+    }
+}
+
+impl Config {
+    /// Parses the arguments after the program name.
+    pub fn parse<S: AsRef<str>>(args: &[S]) -> Result<Config, anyhow::Error> {
+        let mut config = Config::default();
+        let mut args = args.iter().map(AsRef::as_ref);
+
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| args.next().ok_or(anyhow!("{} needs a value", name));
+            match arg {
+                "--depth" => config.depth = Some(value(arg)?.parse()?),
+                "--connect-n" => {
+                    config.connect = match value(arg)?.parse()? {
+                        n @ 3..=5 => n,
+                        n => bail!("--connect-n must be 3, 4 or 5, not {}", n),
+                    }
+                }
+                "--variant" => {
+                    config.pop_out = match value(arg)? {
+                        "pop-out" => true,
+                        "standard" => false,
+                        variant => bail!("unknown variant '{}'", variant),
+                    }
+                }
+                "--plain" => config.plain = true,
+                "--seed" => config.seed = Some(value(arg)?.parse()?),
+                "--hotseat" => config.hotseat = true,
+                "--adaptive" => config.adaptive = true,
+                "--protocol" => config.command = Command::Protocol,
+                "--generate-book" => {
+                    let plies = value(arg)?.parse()?;
+                    let path = value(arg)?.to_string();
+                    config.command = Command::GenerateBook { plies, path };
+                }
+                "--help" | "-h" => config.command = Command::Help,
+                "solve" => config.command = Command::Solve(value(arg)?.to_string()),
+                _ => bail!("unexpected argument '{}'", arg),
+            }
+        }
+
+        Ok(config)
+    }
+
+    /// A REPL set up with these options.
+    pub fn repl(&self) -> Repl {
+        let mut repl = Repl::new().with_connect(self.connect);
+        if let Some(depth) = self.depth {
+            repl = repl.with_depth(depth);
+        }
+        if self.pop_out {
+            repl = repl.with_pop_out();
+        }
+        if self.plain {
+            repl = repl.with_plain();
+        }
+        if let Some(seed) = self.seed {
+            repl = repl.with_seed(seed);
+        }
+        if self.hotseat {
+            repl = repl.with_hotseat();
+        }
+        if self.adaptive {
+            repl = repl.with_adaptive();
+        }
+        repl
+    }
+}
+
+/// The exact score and best move for the side to move, which is found from the piece counts.
+pub fn solve(notation: &str) -> Result<String, anyhow::Error> {
+    let board = Board::from_notation_strict(notation)?;
+    let line = match Minimax::new(&board, board.side_to_move(), 0).solve_best_move() {
+        Some((file, score)) => format!("score {} bestmove {}", score, file + 1),
+        None => "score 0 bestmove none".to_string(),
+    };
+
+    Ok(line)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::board::Color;
+
+    #[test]
+    fn parses_command_lines() {
+        assert_eq!(Config::parse::<&str>(&[]).unwrap(), Config::default());
+
+        let config = Config::parse(&[
+            "--depth",
+            "8",
+            "--connect-n",
+            "5",
+            "--variant",
+            "pop-out",
+            "--plain",
+            "--seed",
+            "42",
+            "--hotseat",
+            "--adaptive",
+        ])
+        .unwrap();
+        assert_eq!(
+            config,
+            Config {
+                command: Command::Play,
+                depth: Some(8),
+                connect: 5,
+                pop_out: true,
+                plain: true,
+                seed: Some(42),
+                hotseat: true,
+                adaptive: true,
+            }
+        );
+
+        let config = Config::parse(&["solve", "7/7/7/7/7/3r3", "--depth", "4"]).unwrap();
+        assert_eq!(config.command, Command::Solve("7/7/7/7/7/3r3".to_string()));
+        assert_eq!(config.depth, Some(4));
+
+        assert_eq!(
+            Config::parse(&["--generate-book", "3", "book.bin"])
+                .unwrap()
+                .command,
+            Command::GenerateBook {
+                plies: 3,
+                path: "book.bin".to_string()
+            }
+        );
+        assert_eq!(
+            Config::parse(&["--protocol"]).unwrap().command,
+            Command::Protocol
+        );
+    }
+
+    #[test]
+    fn solves_for_the_side_a_piece_behind() {
+        // A solved position with 37 pieces, colors swapped so Yellow opened and Red is to move.
+        let notation = "yrrry2/ryryyy1/yrryrr1/yryryy1/rrryyrr/yyryyyr";
+        assert_eq!(
+            Board::from_notation(notation).unwrap().side_to_move(),
+            Color::Red
+        );
+        assert_eq!(solve(notation).unwrap(), "score -1 bestmove 6");
+        assert!(solve("7/7/7/7/7/3r1r1").is_err());
+    }
+
+    #[test]
+    fn rejects_bad_arguments() {
+        for args in [
+            &["--depth"][..],
+            &["--depth", "deep"],
+            &["--connect-n", "6"],
+            &["--variant", "gravity"],
+            &["--frobnicate"],
+            &["solve"],
+        ] {
+            assert!(Config::parse(args).is_err(), "{:?}", args);
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use crate::{
+    board::{Board, Color},
+    book::Book,
+    minimax::Minimax,
+};
+
+/// How much a move gives away compared to the best one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MoveQuality {
+    Best,
+    Good,
+    Inaccuracy,
+    Mistake,
+    Blunder,
+}
+
+impl MoveQuality {
+    /// Classifies a loss in evaluation units. A connect-4 is worth 42 by default, so conceding most
+    /// of one is a blunder.
+    pub fn from_loss(loss: i32) -> Self {
+        match loss {
+            i32::MIN..=0 => MoveQuality::Best,
+            1..=4 => MoveQuality::Good,
+            5..=12 => MoveQuality::Inaccuracy,
+            13..=29 => MoveQuality::Mistake,
+            _ => MoveQuality::Blunder,
+        }
+    }
+}
+
+impl std::fmt::Display for MoveQuality {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            MoveQuality::Best => "best",
+            MoveQuality::Good => "good",
+            MoveQuality::Inaccuracy => "an inaccuracy",
+            MoveQuality::Mistake => "a mistake",
+            MoveQuality::Blunder => "a blunder",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A move compared with the book or, out of book, with a shallow search.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Review {
+    pub played: usize,
+    pub best: usize,
+    /// How much worse the played move evaluates than the best, from the mover's side.
+    pub loss: i32,
+    pub quality: MoveQuality,
+    pub in_book: bool,
+}
+
+/// Reviews `color` playing `file` on `board`, searching `depth` plies when the book has no entry.
+pub fn review_move(board: &Board, color: Color, file: usize, depth: usize, book: &Book) -> Review {
+    if let Some(best) = book.lookup(board).filter(|&best| best == file) {
+        return Review {
+            played: file,
+            best,
+            loss: 0,
+            quality: MoveQuality::Best,
+            in_book: true,
+        };
+    }
+
+    let evaluations = Minimax::new(board, color, depth).analyze();
+    let (best, best_eval) = evaluations[0];
+    let played_eval = evaluations
+        .iter()
+        .find(|(f, _)| *f == file)
+        .map(|(_, eval)| *eval)
+        .unwrap_or(best_eval);
+
+    let loss = match color {
+        Color::Red => best_eval.saturating_sub(played_eval),
+        Color::Yellow => played_eval.saturating_sub(best_eval),
+    };
+
+    Review {
+        played: file,
+        best: book.lookup(board).unwrap_or(best),
+        loss,
+        quality: MoveQuality::from_loss(loss),
+        in_book: false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn classifies_book_move_and_blunder() {
+        let book = Book::standard();
+
+        let review = review_move(&Board::new(), Color::Red, 3, 4, &book);
+        assert_eq!(review.quality, MoveQuality::Best);
+        assert!(review.in_book);
+
+        // Red has two in the middle of the bottom row, so ignoring it lets Red make an open three.
+        let board = Board::from_moves("445").unwrap();
+        let review = review_move(&board, Color::Yellow, 0, 4, &book);
+        assert_eq!(review.quality, MoveQuality::Blunder);
+        assert!(matches!(review.best, 2 | 5));
+        assert!(!review.in_book);
+    }
+}
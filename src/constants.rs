@@ -17,6 +17,12 @@ pub const ROW: [u64; 6] = [
     0x000000000000007F,
 ];
 
+/// Rows 1, 3 and 5 counting from the bottom
+pub const ODD_ROWS: u64 = ROW[0] | ROW[2] | ROW[4];
+
+/// Rows 2, 4 and 6 counting from the bottom
+pub const EVEN_ROWS: u64 = ROW[1] | ROW[3] | ROW[5];
+
 pub const EMPTY_BOARD: u64 = 0x0001FC0000000000;
 
 /// Includes the row beneath the gameboard used for early shifts
@@ -25,4 +31,112 @@ pub const BOARD_MASK: u64 = 0x0001FFFFFFFFFFFF;
 /// Excludes the row beneath the gameboard
 pub const GAME_MASK: u64 = 0x000003FFFFFFFFFF;
 
-// pub const CONNECT4_MASKS: [];
+/// The bottom three cells of the center file, which sit on more lines of four than any others.
+pub const CENTER_STACK: u64 = FILE[3] & (ROW[0] | ROW[1] | ROW[2]);
+
+/// A line direction as a single-cell shift. Forward steps shift right by `shift` (toward the top
+/// of the board), backward steps shift left; cells with no neighbor that way are cleared first so
+/// nothing wraps around to the far side of the board.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Direction {
+    pub shift: u32,
+    /// Cells with no neighbor in the forward direction.
+    pub edge_mask: u64,
+    /// Cells with no neighbor in the backward direction.
+    pub back_edge_mask: u64,
+}
+
+impl Direction {
+    #[inline(always)]
+    pub const fn forward(&self, cells: u64) -> u64 {
+        (cells & !self.edge_mask) >> self.shift
+    }
+
+    #[inline(always)]
+    pub const fn backward(&self, cells: u64) -> u64 {
+        (cells & !self.back_edge_mask) << self.shift
+    }
+}
+
+/// Forward is to the left.
+pub const HORIZONTAL: Direction = Direction {
+    shift: 1,
+    edge_mask: FILE[0],
+    back_edge_mask: FILE[6],
+};
+
+/// Forward is up.
+pub const VERTICAL: Direction = Direction {
+    shift: 7,
+    edge_mask: ROW[5],
+    back_edge_mask: ROW[0],
+};
+
+/// Forward is up and to the right.
+pub const DIAGONAL: Direction = Direction {
+    shift: 6,
+    edge_mask: ROW[5] | FILE[6],
+    back_edge_mask: ROW[0] | FILE[0],
+};
+
+/// Forward is up and to the left.
+pub const ANTIDIAGONAL: Direction = Direction {
+    shift: 8,
+    edge_mask: ROW[5] | FILE[0],
+    back_edge_mask: ROW[0] | FILE[6],
+};
+
+pub const DIRECTIONS: [Direction; 4] = [HORIZONTAL, VERTICAL, DIAGONAL, ANTIDIAGONAL];
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// The cell one step from (row, file) by (rows, files), rows counting down from the top.
+    fn neighbor(row: usize, file: usize, rows: isize, files: isize) -> u64 {
+        let (row, file) = (row as isize + rows, file as isize + files);
+        match (0..6).contains(&row) && (0..7).contains(&file) {
+            true => 1 << (row * 7 + file),
+            false => 0,
+        }
+    }
+
+    #[test]
+    fn directions_step_to_neighbors_and_stop_at_edges() {
+        let steps = [(0, -1), (-1, 0), (-1, 1), (-1, -1)];
+
+        for (direction, (rows, files)) in DIRECTIONS.into_iter().zip(steps) {
+            for row in 0..6 {
+                for file in 0..7 {
+                    let cell = 1 << (row * 7 + file);
+                    assert_eq!(
+                        direction.forward(cell),
+                        neighbor(row, file, rows, files),
+                        "{:?} forward from row {} file {}",
+                        direction,
+                        row,
+                        file
+                    );
+                    assert_eq!(
+                        direction.backward(cell),
+                        neighbor(row, file, -rows, -files),
+                        "{:?} backward from row {} file {}",
+                        direction,
+                        row,
+                        file
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn edge_masks_have_no_forward_neighbors() {
+        for direction in DIRECTIONS {
+            assert_eq!(direction.forward(direction.edge_mask), 0);
+            assert_eq!(direction.backward(direction.back_edge_mask), 0);
+            assert_eq!(direction.forward(GAME_MASK) & !GAME_MASK, 0);
+            assert_eq!(direction.backward(GAME_MASK) & !GAME_MASK, 0);
+        }
+    }
+}
@@ -1,4 +1,15 @@
 pub mod board;
+pub mod book;
+pub mod cli;
+pub mod coach;
 pub mod constants;
+pub mod mcts;
 pub mod minimax;
+pub mod player;
+pub mod protocol;
 pub mod repl;
+#[cfg(feature = "save")]
+pub mod save;
+pub mod share;
+pub mod tournament;
+pub mod tuning;
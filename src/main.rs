@@ -2,15 +2,56 @@ use std::time::Instant;
 
 use connect_4::{
     board::{Board, Color},
-    repl::Repl,
+    book::Book,
+    cli::{self, Command, Config, USAGE},
+    protocol::Protocol,
 };
 
 fn main() {
     // perft_test(10);
-    let repl = Repl::new();
-    repl.start();
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let config = match Config::parse(&args) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    let result = match &config.command {
+        Command::Play => {
+            config.repl().start();
+            Ok(())
+        }
+        Command::Protocol => {
+            let stdin = std::io::stdin();
+            Protocol::new()
+                .run(stdin.lock(), std::io::stdout())
+                .map_err(anyhow::Error::from)
+        }
+        Command::Solve(notation) => cli::solve(notation).map(|line| println!("{}", line)),
+        Command::GenerateBook { plies, path } => generate_book(*plies, path),
+        Command::Help => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+    };
+
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
+
+fn generate_book(plies: usize, path: &str) -> Result<(), anyhow::Error> {
+    let book = Book::generate(plies, 8);
+    book.save(path)?;
+    println!("Wrote {} positions to {}", book.len(), path);
+
+    Ok(())
 }
 
+#[allow(dead_code)]
 fn perft_test(depth: usize) {
     println!("Beginning perft test to depth {}", depth);
     let start = Instant::now();
@@ -26,6 +67,7 @@ fn perft_test(depth: usize) {
     println!("Average NPS: {}", count as f32 / elapsed.as_secs_f32())
 }
 
+#[allow(dead_code)]
 fn perft(depth: usize, board: &mut Board, color: Color) -> usize {
     let mut positions = 1;
 
@@ -37,7 +79,7 @@ fn perft(depth: usize, board: &mut Board, color: Color) -> usize {
     for m in moves {
         board.insert(m, color);
         positions += perft(depth - 1, board, color.other());
-        board.remove(m);
+        let _ = board.remove(m);
     }
 
     positions
@@ -0,0 +1,223 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use arrayvec::ArrayVec;
+use rand::{SeedableRng, rngs::StdRng};
+
+use crate::board::{Board, Color};
+
+/// Exploration constant for UCT selection.
+const EXPLORATION: f64 = std::f64::consts::SQRT_2;
+
+pub struct Mcts<'a> {
+    board: &'a Board,
+    color: Color,
+    iterations: usize,
+    seed: Option<u64>,
+    stop: Arc<AtomicBool>,
+    time_limit: Option<Duration>,
+}
+
+struct Node {
+    board: Board,
+    /// The color to move from this node.
+    to_move: Color,
+    /// The file played to reach this node from its parent.
+    file: usize,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    untried: ArrayVec<usize, 7>,
+    visits: u32,
+    /// Wins for the color that moved into this node, counting draws as half.
+    wins: f64,
+    /// Whether the move into this node connected 4.
+    won: bool,
+}
+
+impl<'a> Mcts<'a> {
+    pub fn new(board: &'a Board, color: Color, iterations: usize) -> Self {
+        Mcts {
+            board,
+            color,
+            iterations,
+            seed: None,
+            stop: Arc::new(AtomicBool::new(false)),
+            time_limit: None,
+        }
+    }
+
+    /// Seeds the rollouts so searches are reproducible.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Shares a flag that, once set, ends the search after the current playout.
+    pub fn with_stop(mut self, stop: Arc<AtomicBool>) -> Self {
+        self.stop = stop;
+        self
+    }
+
+    /// Limits each call to `best_move` to roughly `limit`, after which it plays the most visited
+    /// move so far.
+    pub fn with_time_limit(mut self, limit: Duration) -> Self {
+        self.time_limit = Some(limit);
+        self
+    }
+
+    pub fn best_move(&self) -> usize {
+        self.best_move_with_playouts().0
+    }
+
+    /// Like `best_move`, but also returns how many playouts ran before the search finished or
+    /// was stopped.
+    pub fn best_move_with_playouts(&self) -> (usize, u32) {
+        let deadline = self.time_limit.map(|limit| Instant::now() + limit);
+        let mut rng = match self.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+
+        let mut tree = vec![Node {
+            board: *self.board,
+            to_move: self.color,
+            file: 0,
+            parent: None,
+            children: Vec::new(),
+            untried: self.board.legal_files(),
+            visits: 0,
+            wins: 0.0,
+            won: false,
+        }];
+
+        for _ in 0..self.iterations {
+            let timed_out = deadline.is_some_and(|deadline| Instant::now() >= deadline);
+            if timed_out || self.stop.load(Ordering::Relaxed) {
+                break;
+            }
+
+            // Selection
+            let mut node = 0;
+            while tree[node].untried.is_empty() && !tree[node].children.is_empty() {
+                node = select(&tree, node);
+            }
+
+            // Expansion. Won nodes have no untried moves, so they are scored directly.
+            if let Some(file) = tree[node].untried.pop() {
+                let mut board = tree[node].board;
+                let color = tree[node].to_move;
+                board.insert(file, color);
+                let won = board.last_move_wins(file);
+
+                tree.push(Node {
+                    board,
+                    to_move: color.other(),
+                    file,
+                    parent: Some(node),
+                    children: Vec::new(),
+                    untried: match won {
+                        true => ArrayVec::new(),
+                        false => board.legal_files(),
+                    },
+                    visits: 0,
+                    wins: 0.0,
+                    won,
+                });
+                let child = tree.len() - 1;
+                tree[node].children.push(child);
+                node = child;
+            }
+
+            // Simulation
+            let winner = match tree[node].won {
+                true => Some(tree[node].to_move.other()),
+                false => {
+                    let mut board = tree[node].board;
+                    board.random_playout(tree[node].to_move, &mut rng)
+                }
+            };
+
+            // Backpropagation
+            let mut current = Some(node);
+            while let Some(index) = current {
+                let node = &mut tree[index];
+                node.visits += 1;
+                node.wins += match winner {
+                    Some(winner) if winner == node.to_move.other() => 1.0,
+                    Some(_) => 0.0,
+                    None => 0.5,
+                };
+                current = node.parent;
+            }
+        }
+
+        let file = tree[0]
+            .children
+            .iter()
+            .max_by_key(|&&child| tree[child].visits)
+            .map(|&child| tree[child].file)
+            .unwrap_or_else(|| self.board.legal_files()[0]);
+        (file, tree[0].visits)
+    }
+}
+
+fn select(tree: &[Node], node: usize) -> usize {
+    let parent_visits = (tree[node].visits as f64).ln();
+
+    let uct = |child: usize| {
+        let child = &tree[child];
+        let visits = child.visits as f64;
+        child.wins / visits + EXPLORATION * (parent_visits / visits).sqrt()
+    };
+
+    tree[node]
+        .children
+        .iter()
+        .copied()
+        .max_by(|&a, &b| uct(a).total_cmp(&uct(b)))
+        .expect("selected node has children")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn finds_mate_in_one() {
+        let board = Board::from_notation("7/7/7/7/y1yy3/r1rrr2").unwrap();
+        let mcts = Mcts::new(&board, Color::Red, 5000).with_seed(355);
+
+        assert_eq!(mcts.best_move(), 1);
+    }
+
+    #[test]
+    fn blocks_mate_in_one() {
+        let board = Board::from_notation("7/7/7/7/r1r4/yyy1r2").unwrap();
+        let mcts = Mcts::new(&board, Color::Red, 5000).with_seed(355);
+
+        assert_eq!(mcts.best_move(), 3);
+    }
+
+    #[test]
+    fn stop_flag_ends_search_early() {
+        let board = Board::new();
+        let stop = Arc::new(AtomicBool::new(true));
+        let mcts = Mcts::new(&board, Color::Red, 1_000_000).with_stop(stop);
+
+        let (file, playouts) = mcts.best_move_with_playouts();
+        assert!(board.legal_files().contains(&file));
+        assert_eq!(playouts, 0);
+    }
+
+    #[test]
+    fn time_limit_ends_search() {
+        let board = Board::new();
+        let mcts =
+            Mcts::new(&board, Color::Red, usize::MAX).with_time_limit(Duration::from_millis(50));
+
+        let (file, playouts) = mcts.best_move_with_playouts();
+        assert!(board.legal_files().contains(&file));
+        assert!(playouts > 0);
+    }
+}
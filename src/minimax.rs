@@ -1,11 +1,142 @@
-use std::cmp;
+use std::{
+    cell::{Cell, RefCell},
+    cmp,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
 
-use crate::board::{Board, Color};
+use arrayvec::ArrayVec;
+
+use crate::{
+    board::{Board, Color, EvalWeights},
+    constants::{FILE, GAME_MASK, ROW},
+};
+
+/// Files ordered center first, where the most lines run, so cutoffs come early.
+const CENTER_FIRST: [usize; 7] = [3, 2, 4, 1, 5, 0, 6];
+
+/// Bounds every search score, in place of `i32::MIN` and `i32::MAX`, so alpha-beta windows can
+/// be negated or widened without overflowing. Evaluations stay far inside it.
+pub const INF: i32 = 1_000_000;
+
+/// A win scores `INF` less the plies from the search root to the winning move, so nearer wins
+/// score higher. Every such score is beyond `MATE`.
+pub const MATE: i32 = INF - 100;
+
+/// Horizon evaluations are clamped to within this, far short of `MATE`.
+pub const HEURISTIC_BOUND: i32 = 10_000;
+
+/// Piece count from which `Minimax` solves positions exactly instead of evaluating them.
+pub const SOLVE_FROM: usize = 30;
+
+/// Scores a position at the search horizon for the given side to move, positive favoring Red.
+pub type Evaluator = Box<EvaluateFn>;
+
+type EvaluateFn = dyn Fn(&Board, Color) -> i32 + Send + Sync;
+
+/// A sequence of moves, each with the color that played it.
+pub type Line = Vec<(Color, usize)>;
+
+/// What a search found, for logging engine decisions.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SearchStats {
+    /// The evaluation of the chosen move, positive favoring Red.
+    pub score: i32,
+    /// The deepest completed iteration.
+    pub depth: usize,
+    pub nodes: u64,
+}
+
+/// A style of play layered over the evaluation, for more varied opponents.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Personality {
+    /// Added to the engine's score for each root move, favoring some files over others.
+    pub column_bias: [i32; 7],
+    /// Bonus per empty cell that would complete a four for the engine.
+    pub aggression: i32,
+    /// Penalty per empty cell that would complete a four for the opponent.
+    pub caution: i32,
+}
+
+impl Personality {
+    /// Plays for threats of its own.
+    pub fn aggressive() -> Self {
+        Personality {
+            aggression: 8,
+            ..Personality::default()
+        }
+    }
+
+    /// Plays to deny the opponent threats.
+    pub fn defensive() -> Self {
+        Personality {
+            caution: 8,
+            ..Personality::default()
+        }
+    }
+
+    /// The adjustment to a leaf evaluation for an engine playing `color`, positive favoring Red.
+    #[inline(always)]
+    fn adjustment(&self, board: &Board, color: Color) -> i32 {
+        if self.aggression == 0 && self.caution == 0 {
+            return 0;
+        }
+
+        let own = board.threats(color).count_ones() as i32;
+        let opponent = board.threats(color.other()).count_ones() as i32;
+        let score = own * self.aggression - opponent * self.caution;
+        match color {
+            Color::Red => score,
+            Color::Yellow => -score,
+        }
+    }
+
+    fn bias(&self, file: usize, color: Color) -> i32 {
+        match color {
+            Color::Red => self.column_bias[file],
+            Color::Yellow => -self.column_bias[file],
+        }
+    }
+}
+
+/// The order the search tries moves in. Good moves first mean earlier cutoffs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MoveOrdering {
+    /// Most tactically promising first, by `Board::threats_created_by`.
+    #[default]
+    Threats,
+    /// Center files first, where the most lines run.
+    CenterOut,
+}
+
+/// What `best_move_for` plays for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Objective {
+    /// The best move by score, as `best_move` plays.
+    Win,
+    /// In a lost position, the move that holds out the most plies against best play.
+    DelayLoss,
+    /// Of the moves that don't lose by force, the one leaving the opponent the most replies and
+    /// so the most chances to go wrong.
+    MaximizeComplexity,
+}
 
 pub struct Minimax<'a> {
     board: &'a Board,
     color: Color,
     depth: usize,
+    stop: Arc<AtomicBool>,
+    time_limit: Option<Duration>,
+    weights: EvalWeights,
+    personality: Personality,
+    /// The line length that wins, for Connect-N variants.
+    connect: usize,
+    ordering: MoveOrdering,
+    solve_from: Option<usize>,
+    evaluator: Option<Evaluator>,
 }
 
 impl<'a> Minimax<'a> {
@@ -14,12 +145,292 @@ impl<'a> Minimax<'a> {
             board,
             color,
             depth,
+            stop: Arc::new(AtomicBool::new(false)),
+            time_limit: None,
+            weights: EvalWeights::default(),
+            personality: Personality::default(),
+            connect: 4,
+            ordering: MoveOrdering::default(),
+            solve_from: Some(SOLVE_FROM),
+            evaluator: None,
         }
     }
 
+    /// Shares a flag that, once set, ends the search and returns the best move from the deepest
+    /// completed iteration.
+    pub fn with_stop(mut self, stop: Arc<AtomicBool>) -> Self {
+        self.stop = stop;
+        self
+    }
+
+    pub fn with_weights(mut self, weights: EvalWeights) -> Self {
+        self.weights = weights;
+        self
+    }
+
+    /// Plays Connect-N, where `n` in a row wins, instead of Connect-4.
+    pub fn with_connect(mut self, n: usize) -> Self {
+        self.connect = n;
+        self
+    }
+
+    pub fn with_ordering(mut self, ordering: MoveOrdering) -> Self {
+        self.ordering = ordering;
+        self
+    }
+
+    /// Solves positions with at least `pieces` on the board to the end instead of evaluating
+    /// them, or never with None. Solved results score as wins and draws found by the search
+    /// do. Only Connect-4 is solved.
+    pub fn with_solver_threshold(mut self, pieces: Option<usize>) -> Self {
+        self.solve_from = pieces;
+        self
+    }
+
+    /// Scores horizon positions with `evaluator` in place of the weighted heuristic and
+    /// personality. Solved positions still score exactly unless the solver is turned off.
+    pub fn with_evaluator(
+        mut self,
+        evaluator: impl Fn(&Board, Color) -> i32 + Send + Sync + 'static,
+    ) -> Self {
+        self.evaluator = Some(Box::new(evaluator));
+        self
+    }
+
+    pub fn with_personality(mut self, personality: Personality) -> Self {
+        self.personality = personality;
+        self
+    }
+
+    /// Limits each call to `best_move` to roughly `limit`, after which it plays the best move
+    /// from the deepest completed iteration.
+    pub fn with_time_limit(mut self, limit: Duration) -> Self {
+        self.time_limit = Some(limit);
+        self
+    }
+
     pub fn best_move(&self) -> usize {
-        let files = self.board.legal_files();
-        let mut evaluations: Vec<Eval> = files
+        self.best_move_with_stats().0
+    }
+
+    /// Like `best_move`, also returning the score, depth and node count behind the choice.
+    pub fn best_move_with_stats(&self) -> (usize, SearchStats) {
+        let forced = self
+            .board
+            .find_immediate_win_n(self.color, self.connect)
+            .or_else(|| {
+                self.board
+                    .find_immediate_win_n(self.color.other(), self.connect)
+            });
+        if let Some(file) = forced {
+            let mut board = *self.board;
+            board.insert(file, self.color);
+            let search = self.search();
+            let stats = SearchStats {
+                score: match board.has_connect_n(self.color, self.connect) {
+                    true => search.mate(self.color, &board),
+                    false => search.evaluate(&board, self.color.other()),
+                },
+                depth: 0,
+                nodes: 0,
+            };
+            return (file, stats);
+        }
+
+        let search = self.search();
+        let Eval(mut file, mut score) = self.search_root(0, &search);
+        let mut completed = 0;
+
+        // The depth 0 iteration never checks the stop flag, so there is always a move to play.
+        for depth in 1..=self.depth {
+            let best = self.search_root(depth, &search);
+            if search.stopped() {
+                break;
+            }
+            Eval(file, score) = best;
+            completed = depth;
+        }
+
+        let stats = SearchStats {
+            score,
+            depth: completed,
+            nodes: search.nodes.get(),
+        };
+        (file, stats)
+    }
+
+    /// Every legal move with its evaluation at the full depth, ordered best first for the side
+    /// to move.
+    pub fn analyze(&self) -> Vec<(usize, i32)> {
+        let search = self.search();
+        let mut evaluations = self.evaluate_root(self.depth, &search);
+        self.sort_best_first(&mut evaluations);
+
+        evaluations
+            .into_iter()
+            .map(|Eval(file, eval)| (file, eval))
+            .collect()
+    }
+
+    /// Every legal move whose evaluation at the full depth ties the best for the side to move, in
+    /// file order.
+    pub fn all_best_moves(&self) -> ArrayVec<usize, 7> {
+        let evaluations = self.analyze();
+        let Some(&(_, best)) = evaluations.first() else {
+            return ArrayVec::new();
+        };
+
+        let mut files = evaluations
+            .into_iter()
+            .filter(|&(_, eval)| eval == best)
+            .map(|(file, _)| file)
+            .collect::<ArrayVec<_, 7>>();
+        files.sort_unstable();
+        files
+    }
+
+    /// The best `k` root moves, each with its evaluation and principal variation (starting with
+    /// the root move itself), ordered best first for the side to move.
+    pub fn multi_pv(&self, k: usize) -> Vec<(usize, i32, Line)> {
+        let search = self.search();
+        let mut evaluations = self.evaluate_root(self.depth, &search);
+        self.sort_best_first(&mut evaluations);
+
+        evaluations
+            .into_iter()
+            .take(k)
+            .map(|Eval(file, eval)| {
+                let mut board = *self.board;
+                board.insert(file, self.color);
+
+                let mut line = vec![(self.color, file)];
+                if !board.has_connect_n(self.color, self.connect) {
+                    line.extend(principal_variation(
+                        &mut board,
+                        self.color.other(),
+                        self.depth,
+                        &search,
+                    ));
+                }
+
+                (file, eval, line)
+            })
+            .collect()
+    }
+
+    /// The legal moves that don't lose by force within `depth` plies, in file order.
+    pub fn safe_moves(&self) -> Vec<usize> {
+        self.board
+            .legal_files()
+            .into_iter()
+            .filter(|&file| {
+                let mut board = *self.board;
+                board.insert(file, self.color);
+                board.has_connect_n(self.color, self.connect)
+                    || !wins_by_force(&mut board, self.color.other(), self.depth, self.connect)
+            })
+            .collect()
+    }
+
+    /// The best move for `objective`. Ties go to the move `analyze` ranks higher.
+    pub fn best_move_for(&self, objective: Objective) -> usize {
+        // `max_by_key` keeps the last of equal keys, so search from the lowest ranked.
+        let worst_first = || self.analyze().into_iter().rev().map(|(file, _)| file);
+
+        match objective {
+            Objective::Win => self.best_move(),
+            Objective::DelayLoss => worst_first()
+                .max_by_key(|&file| self.plies_to_loss(file))
+                .expect("a legal move"),
+            Objective::MaximizeComplexity => {
+                let safe = self.safe_moves();
+                worst_first()
+                    .filter(|file| safe.is_empty() || safe.contains(file))
+                    .max_by_key(|&file| {
+                        let mut board = *self.board;
+                        board.insert(file, self.color);
+                        board.legal_files().len()
+                    })
+                    .expect("a legal move")
+            }
+        }
+    }
+
+    /// How many plies after playing `file` the opponent needs to force a win, counting its
+    /// winning move; `usize::MAX` if `file` wins or no forced loss is found within `depth`.
+    pub fn plies_to_loss(&self, file: usize) -> usize {
+        let mut board = *self.board;
+        board.insert(file, self.color);
+        if board.has_connect_n(self.color, self.connect) {
+            return usize::MAX;
+        }
+
+        // The opponent only wins on its own moves: the first, third, fifth ply and so on.
+        (1..=self.depth.max(1))
+            .step_by(2)
+            .find(|&plies| wins_by_force(&mut board, self.color.other(), plies, self.connect))
+            .unwrap_or(usize::MAX)
+    }
+
+    /// Solves the position exactly by searching to the end of the game, ignoring `depth`. The
+    /// score follows the usual solver convention: 0 for a draw, otherwise positive if the side to
+    /// move wins, scoring (43 - pieces)/2 counting the pieces on the board just before the winning
+    /// move, so sooner wins score higher. Only practical with a good part of the board
+    /// filled.
+    pub fn solve_exact(&self) -> i32 {
+        let mut board = *self.board;
+        solve(&mut board, self.color, &mut TranspositionTable::new())
+    }
+
+    /// The move `solve_exact` is scored by, with its score, or None if there are no legal moves.
+    /// Ties go to the lowest file.
+    pub fn solve_best_move(&self) -> Option<(usize, i32)> {
+        let pieces = self.board.piece_count() as i32;
+        let mut table = TranspositionTable::new();
+        let mut best: Option<(usize, i32)> = None;
+        for file in self.board.legal_files() {
+            let mut board = *self.board;
+            board.insert(file, self.color);
+            let score = match board.last_move_wins(file) {
+                true => (43 - pieces) / 2,
+                false => -solve(&mut board, self.color.other(), &mut table),
+            };
+            if best.is_none_or(|(_, best)| score > best) {
+                best = Some((file, score));
+            }
+        }
+
+        best
+    }
+
+    fn search(&self) -> Search<'_> {
+        Search {
+            stop: &self.stop,
+            deadline: self.time_limit.map(|limit| Instant::now() + limit),
+            weights: &self.weights,
+            personality: &self.personality,
+            color: self.color,
+            connect: self.connect,
+            ordering: self.ordering,
+            solve_from: self.solve_from.filter(|_| self.connect == 4),
+            evaluator: self.evaluator.as_deref(),
+            root_pieces: self.board.piece_count(),
+            nodes: Cell::new(0),
+            timed_out: Cell::new(false),
+            table: RefCell::new(None),
+        }
+    }
+
+    fn search_root(&self, depth: usize, search: &Search) -> Eval {
+        let mut evaluations = self.evaluate_root(depth, search);
+        self.sort_best_first(&mut evaluations);
+
+        evaluations.swap_remove(0)
+    }
+
+    fn evaluate_root(&self, depth: usize, search: &Search) -> Vec<Eval> {
+        self.board
+            .legal_files()
             .into_iter()
             .map(|file| {
                 let mut possible_board = *self.board;
@@ -27,20 +438,24 @@ impl<'a> Minimax<'a> {
                 let eval = minimax(
                     &mut possible_board,
                     self.color.other(),
-                    self.depth,
-                    i32::MIN,
-                    i32::MAX,
+                    depth,
+                    -INF,
+                    INF,
+                    search,
                 );
-                Eval(file, eval)
+                Eval(
+                    file,
+                    eval.saturating_add(self.personality.bias(file, self.color)),
+                )
             })
-            .collect();
+            .collect()
+    }
 
+    fn sort_best_first(&self, evaluations: &mut [Eval]) {
         match self.color {
             Color::Red => evaluations.sort_unstable_by(|a, b| b.cmp(a)),
             Color::Yellow => evaluations.sort_unstable(),
         }
-
-        evaluations[0].0
     }
 }
 
@@ -59,9 +474,376 @@ impl PartialOrd for Eval {
     }
 }
 
-fn minimax(board: &mut Board, color: Color, depth: usize, alpha: i32, beta: i32) -> i32 {
+/// State shared by every node of a single search.
+struct Search<'a> {
+    stop: &'a AtomicBool,
+    deadline: Option<Instant>,
+    weights: &'a EvalWeights,
+    personality: &'a Personality,
+    /// The engine's color, which the personality plays for.
+    color: Color,
+    connect: usize,
+    ordering: MoveOrdering,
+    solve_from: Option<usize>,
+    evaluator: Option<&'a EvaluateFn>,
+    /// Pieces on the board at the root, for counting the plies to a win.
+    root_pieces: usize,
+    nodes: Cell<u64>,
+    timed_out: Cell<bool>,
+    /// The exact solver's table, allocated by the first solve.
+    table: RefCell<Option<TranspositionTable>>,
+}
+
+impl Search<'_> {
+    /// Counts a node, checking the deadline every 1024 nodes to keep the clock off the hot path.
+    #[inline(always)]
+    fn visit(&self) {
+        let nodes = self.nodes.get() + 1;
+        self.nodes.set(nodes);
+
+        if nodes & 1023 == 0
+            && self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            self.timed_out.set(true);
+        }
+    }
+
+    #[inline(always)]
+    fn stopped(&self) -> bool {
+        self.timed_out.get() || self.stop.load(Ordering::Relaxed)
+    }
+
+    /// The score of a horizon position with `color` to move, within `HEURISTIC_BOUND`.
+    #[inline(always)]
+    fn evaluate(&self, board: &Board, color: Color) -> i32 {
+        let score = match self.evaluator {
+            Some(evaluator) => evaluator(board, color),
+            None => {
+                board.evaluate_connect_n(self.connect, self.weights, color)
+                    + self.personality.adjustment(board, self.color)
+            }
+        };
+        score.clamp(-HEURISTIC_BOUND, HEURISTIC_BOUND)
+    }
+
+    /// The score of `winner` having just won on `board`.
+    fn mate(&self, winner: Color, board: &Board) -> i32 {
+        self.mate_in(winner, board.piece_count() - self.root_pieces)
+    }
+
+    /// The score of `winner` winning with the move `plies` plies after the root.
+    fn mate_in(&self, winner: Color, plies: usize) -> i32 {
+        let score = INF - plies as i32;
+        match winner {
+            Color::Red => score,
+            Color::Yellow => -score,
+        }
+    }
+
+    fn ordered_files(&self, board: &Board, color: Color) -> ArrayVec<usize, 7> {
+        match self.ordering {
+            MoveOrdering::Threats => board.files_by_threats(color),
+            MoveOrdering::CenterOut => CENTER_FIRST
+                .into_iter()
+                .filter(|&file| board.height(file) < 6)
+                .collect(),
+        }
+    }
+}
+
+/// Follows the best reply at each ply for `depth` plies, or until someone connects N.
+fn principal_variation(board: &mut Board, color: Color, depth: usize, search: &Search) -> Line {
+    let mut line = Vec::new();
+    let mut color = color;
+
+    for remaining in (0..depth).rev() {
+        let mut best: Option<Eval> = None;
+        for file in board.legal_files() {
+            board.insert(file, color);
+            let eval = Eval(
+                file,
+                minimax(board, color.other(), remaining, -INF, INF, search),
+            );
+            let _ = board.remove(file);
+
+            best = match (best, color) {
+                (Some(best), Color::Red) if best >= eval => Some(best),
+                (Some(best), Color::Yellow) if best <= eval => Some(best),
+                _ => Some(eval),
+            };
+        }
+
+        let Some(Eval(file, _)) = best else { break };
+        board.insert(file, color);
+        line.push((color, file));
+        if board.has_connect_n(color, search.connect) {
+            break;
+        }
+        color = color.other();
+    }
+
+    line
+}
+
+/// The exact value of the position for the search, positive favoring Red, with `color` to move.
+/// Wins score by the ply of the winning move as they do when the search finds them.
+fn solved_score(board: &mut Board, color: Color, search: &Search) -> i32 {
+    let pieces = board.piece_count();
+    let mut table = search.table.borrow_mut();
+    let table = table.get_or_insert_with(TranspositionTable::new);
+    let score = solve(board, color, table);
+    if score == 0 {
+        return 0;
+    }
+
+    // `solve_exact` scores a win (43 - n) / 2 for the n pieces down before the winning move, and
+    // n has the parity of the winner's turns.
+    let (winner, winner_pieces) = match score > 0 {
+        true => (color, pieces),
+        false => (color.other(), pieces + 1),
+    };
+    let before = 43 - 2 * score.unsigned_abs() as usize - (winner_pieces + 1) % 2;
+    search.mate_in(winner, before + 1 - search.root_pieces)
+}
+
+/// Solves the position with `color` to move exactly, scored as in `solve_exact`. Probes null
+/// windows that close in on the score, starting near zero, since proving a win or loss at all is
+/// far cheaper than pinning down how soon it comes.
+fn solve(board: &mut Board, color: Color, table: &mut TranspositionTable) -> i32 {
+    let pieces = board.piece_count() as i32;
+    if board.find_immediate_win(color).is_some() {
+        return (43 - pieces) / 2;
+    }
+
+    let (mut min, mut max) = (-(42 - pieces) / 2, (43 - pieces) / 2);
+    while min < max {
+        let mut mid = min + (max - min) / 2;
+        if mid <= 0 && min / 2 < mid {
+            mid = min / 2;
+        } else if mid >= 0 && max / 2 > mid {
+            mid = max / 2;
+        }
+
+        let score = negamax(board, color, mid, mid + 1, table);
+        match score <= mid {
+            true => max = score,
+            false => min = score,
+        }
+    }
+
+    min
+}
+
+/// Alpha-beta over exact game outcomes, scored for `color` to move as in `solve_exact`. `color`
+/// must not have a winning move; `solve` checks for one first.
+fn negamax(
+    board: &mut Board,
+    color: Color,
+    alpha: i32,
+    beta: i32,
+    table: &mut TranspositionTable,
+) -> i32 {
+    let pieces = board.piece_count() as i32;
+    let moves = non_losing_moves(board, color);
+    if moves == 0 {
+        return -(42 - pieces) / 2;
+    }
+    if pieces >= 40 {
+        return 0;
+    }
+
+    // The opponent can't win with its next move, so at worst it wins with the one after.
+    let alpha = alpha.max(-(40 - pieces) / 2);
+    if alpha >= beta {
+        return alpha;
+    }
+
+    // Without an immediate win, the best possible result is winning with our next move.
+    let key = TranspositionTable::key(board);
+    let mut beta = beta.min((41 - pieces) / 2);
+    let mut alpha = alpha;
+    match table.get(key) {
+        Some(Bound::Lower(bound)) => alpha = alpha.max(bound),
+        Some(Bound::Upper(bound)) => beta = beta.min(bound),
+        None => {}
+    }
+    if alpha >= beta {
+        return alpha;
+    }
+
+    // Moves that leave the most threats behind are the likeliest to cut off.
+    let mut ordered: ArrayVec<(u32, usize), 7> = CENTER_FIRST
+        .into_iter()
+        .filter(|&file| moves & FILE[file] != 0)
+        .map(|file| {
+            board.insert(file, color);
+            let threats = board.threats(color).count_ones();
+            let _ = board.remove(file);
+            (threats, file)
+        })
+        .collect();
+    ordered.sort_by_key(|&(threats, _)| cmp::Reverse(threats));
+
+    for (_, file) in ordered {
+        board.insert(file, color);
+        let score = -negamax(board, color.other(), -beta, -alpha, table);
+        let _ = board.remove(file);
+
+        if score >= beta {
+            table.put(key, Bound::Lower(score));
+            return score;
+        }
+        alpha = alpha.max(score);
+    }
+
+    table.put(key, Bound::Upper(alpha));
+    alpha
+}
+
+/// The cells `color` can play without letting the opponent win next move: none if the opponent
+/// has two playable wins, only the block if it has one, and never the cell beneath an opposing
+/// threat.
+fn non_losing_moves(board: &Board, color: Color) -> u64 {
+    let threats = board.threats(color.other());
+    let mut moves = board.next_cells();
+    let forced = moves & threats;
+    if forced != 0 {
+        if forced.count_ones() > 1 {
+            return 0;
+        }
+        moves = forced;
+    }
+
+    moves & !(threats << 7)
+}
+
+/// Entries in a `TranspositionTable`, a prime so keys spread evenly.
+const TABLE_SIZE: usize = 8_388_593;
+
+/// A limit on a position's exact score, from a search that cut off (`Lower`) or that found
+/// nothing better (`Upper`).
+#[derive(Clone, Copy)]
+enum Bound {
+    Lower(i32),
+    Upper(i32),
+}
+
+/// Bounds on the solver's scores for positions it has searched, so transpositions are only
+/// searched once. The key doesn't record the side to move, so a table must only hold positions
+/// reached from a single root. The table is allocated zeroed, so only the pages a solve touches
+/// cost memory.
+struct TranspositionTable(Vec<u64>);
+
+impl TranspositionTable {
+    fn new() -> Self {
+        TranspositionTable(vec![0; TABLE_SIZE])
+    }
+
+    /// The red pieces and the landing cell in each file identify a position exactly, once full
+    /// files, which have no landing cell, are marked in the bits above the board. A position and
+    /// its mirror image score the same, so they share the lesser of their keys.
+    fn key(board: &Board) -> u64 {
+        let full = board.all() & ROW[5];
+        let key = (board.red_bits() & GAME_MASK) | board.next_cells() | full << 42;
+        let mirrored = (key & FILE[0]) << 6
+            | (key & FILE[1]) << 4
+            | (key & FILE[2]) << 2
+            | key & FILE[3]
+            | (key & FILE[4]) >> 2
+            | (key & FILE[5]) >> 4
+            | (key & FILE[6]) >> 6;
+        key.min(mirrored)
+    }
+
+    /// Each entry packs the key above a flag for lower bounds and the bound in the low byte.
+    fn get(&self, key: u64) -> Option<Bound> {
+        let entry = self.0[(key % TABLE_SIZE as u64) as usize];
+        if entry >> 9 != key {
+            return None;
+        }
+
+        let bound = entry as u8 as i8 as i32;
+        Some(match entry & 1 << 8 {
+            0 => Bound::Upper(bound),
+            _ => Bound::Lower(bound),
+        })
+    }
+
+    fn put(&mut self, key: u64, bound: Bound) {
+        let (lower, bound) = match bound {
+            Bound::Lower(bound) => (1, bound),
+            Bound::Upper(bound) => (0, bound),
+        };
+        self.0[(key % TABLE_SIZE as u64) as usize] =
+            key << 9 | lower << 8 | bound as i8 as u8 as u64;
+    }
+}
+
+/// Whether `color`, to move, can force a line of `connect` within `plies` plies, whatever the
+/// opponent does.
+fn wins_by_force(board: &mut Board, color: Color, plies: usize, connect: usize) -> bool {
+    if plies == 0 {
+        return false;
+    }
+    if board.find_immediate_win_n(color, connect).is_some() {
+        return true;
+    }
+    // Otherwise winning takes a move, a reply and another move.
+    if plies < 3 {
+        return false;
+    }
+
+    board.legal_files().into_iter().any(|file| {
+        board.insert(file, color);
+        let replies = board.legal_files();
+        let forced = !replies.is_empty()
+            && board.find_immediate_win_n(color.other(), connect).is_none()
+            && replies.into_iter().all(|reply| {
+                board.insert(reply, color.other());
+                let forced = wins_by_force(board, color, plies - 2, connect);
+                let _ = board.remove(reply);
+                forced
+            });
+        let _ = board.remove(file);
+
+        forced
+    })
+}
+
+fn minimax(
+    board: &mut Board,
+    color: Color,
+    depth: usize,
+    alpha: i32,
+    beta: i32,
+    search: &Search,
+) -> i32 {
+    search.visit();
+
+    // The game ends with a line or a full board, so the search never plays on past either.
+    if board.has_connect_n(color.other(), search.connect) {
+        return search.mate(color.other(), board);
+    }
+    if board.is_full() {
+        return 0;
+    }
+
+    if search
+        .solve_from
+        .is_some_and(|pieces| board.piece_count() >= pieces)
+    {
+        return solved_score(board, color, search);
+    }
+
     if depth == 0 {
-        return board.evaluate();
+        return search.evaluate(board, color);
+    }
+
+    // The result is discarded by `best_move` once stopped, so any score will do.
+    if search.stopped() {
+        return 0;
     }
 
     let mut alpha = alpha;
@@ -69,11 +851,11 @@ fn minimax(board: &mut Board, color: Color, depth: usize, alpha: i32, beta: i32)
 
     match color {
         Color::Red => {
-            let mut highest_score = i32::MIN;
-            for file in board.legal_files() {
+            let mut highest_score = -INF;
+            for file in search.ordered_files(board, color) {
                 board.insert(file, color);
-                let score = minimax(board, color.other(), depth - 1, alpha, beta);
-                board.remove(file);
+                let score = minimax(board, color.other(), depth - 1, alpha, beta, search);
+                let _ = board.remove(file);
 
                 highest_score = cmp::max(score, highest_score);
                 alpha = cmp::max(highest_score, alpha);
@@ -86,11 +868,11 @@ fn minimax(board: &mut Board, color: Color, depth: usize, alpha: i32, beta: i32)
             highest_score
         }
         Color::Yellow => {
-            let mut lowest_score = i32::MAX;
-            for file in board.legal_files() {
+            let mut lowest_score = INF;
+            for file in search.ordered_files(board, color) {
                 board.insert(file, color);
-                let score = minimax(board, color.other(), depth - 1, alpha, beta);
-                board.remove(file);
+                let score = minimax(board, color.other(), depth - 1, alpha, beta, search);
+                let _ = board.remove(file);
 
                 lowest_score = cmp::min(score, lowest_score);
                 beta = cmp::min(lowest_score, beta);
@@ -104,3 +886,392 @@ fn minimax(board: &mut Board, color: Color, depth: usize, alpha: i32, beta: i32)
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use crate::constants::FILE;
+
+    use super::*;
+
+    #[test]
+    fn takes_immediate_win() {
+        let board = Board::from_notation("7/7/7/7/y1yy3/r1rrr2").unwrap();
+        let minimax = Minimax::new(&board, Color::Red, 1);
+
+        assert_eq!(minimax.best_move(), 1);
+    }
+
+    #[test]
+    fn blocks_immediate_loss() {
+        let board = Board::from_notation("7/7/7/y6/y6/yrr4").unwrap();
+        let minimax = Minimax::new(&board, Color::Red, 1);
+
+        assert_eq!(minimax.best_move(), 0);
+    }
+
+    #[test]
+    fn reports_search_stats() {
+        let board = Board::from_notation("7/7/7/7/3y3/2rr3").unwrap();
+        let minimax = Minimax::new(&board, Color::Yellow, 3);
+
+        let (file, stats) = minimax.best_move_with_stats();
+        let (best, eval, _) = minimax.multi_pv(1).remove(0);
+
+        assert_eq!((file, stats.score), (best, eval));
+        assert_eq!(stats.depth, 3);
+        assert!(stats.nodes > 0);
+    }
+
+    #[test]
+    fn solves_exact_scores() {
+        // Win in 1 with 7 pieces down.
+        let board = Board::from_notation("7/7/7/7/y1yy3/r1rrr2").unwrap();
+        assert_eq!(Minimax::new(&board, Color::Red, 0).solve_exact(), 18);
+
+        // Red has an open three on the bottom row, so Red wins with the 7th piece.
+        let board = Board::from_moves("44556").unwrap();
+        assert_eq!(Minimax::new(&board, Color::Yellow, 0).solve_exact(), -18);
+
+        let board =
+            Board::from_notation("r1ry1ry/yryyyry/yyyrrry/rrryyyr/ryyryyy/yyyryry").unwrap();
+        assert_eq!(Minimax::new(&board, Color::Red, 0).solve_exact(), 0);
+        assert_eq!(
+            Minimax::new(&board, Color::Red, 0).solve_best_move(),
+            Some((1, 0))
+        );
+
+        let board = Board::from_notation("7/7/7/7/y1yy3/r1rrr2").unwrap();
+        assert_eq!(
+            Minimax::new(&board, Color::Red, 0).solve_best_move(),
+            Some((1, 18))
+        );
+    }
+
+    #[test]
+    fn finds_every_tied_best_move() {
+        let minimax = |moves: &str| {
+            let board = Board::from_moves(moves).unwrap();
+            Minimax::new(&board, Color::Red, 4).all_best_moves()
+        };
+
+        assert_eq!(minimax("").as_slice(), [3]);
+        // With the center stacked, the files either side of it mirror each other.
+        assert_eq!(minimax("4444").as_slice(), [2, 4]);
+        assert_eq!(minimax("444444").as_slice(), [2, 4]);
+
+        let full = Board::from_notation("ryryryr/ryryryr/yryryry/yryryry/ryryryr/ryryryr").unwrap();
+        assert!(
+            Minimax::new(&full, Color::Red, 4)
+                .all_best_moves()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn searches_with_a_custom_evaluator() {
+        let board = Board::from_moves("4453").unwrap();
+        let constant = Minimax::new(&board, Color::Red, 3).with_evaluator(|_, _| 7);
+        assert!(constant.analyze().iter().all(|&(_, eval)| eval == 7));
+
+        // Red's pieces in the leftmost file are all that count.
+        let left = |board: &Board, _| (board.red_bits() & FILE[0]).count_ones() as i32;
+        for depth in 1..=3 {
+            let minimax = Minimax::new(&board, Color::Red, depth).with_evaluator(left);
+            assert_eq!(minimax.best_move(), 0);
+        }
+    }
+
+    #[test]
+    fn nearer_wins_score_higher() {
+        let score = |notation: &str| {
+            let board = Board::from_notation(notation).unwrap();
+            let minimax = Minimax::new(&board, Color::Red, 7).with_solver_threshold(None);
+            minimax.best_move_with_stats().1.score
+        };
+
+        // Red's second and fourth moves from here win by force.
+        let in_two = score("7/4r2/4y2/3ry1y/r2yrry/yy1ryrr");
+        let in_four = score("7/4y2/4r2/2y1r1r/2yrr1y/yrryyyr");
+        assert_eq!(in_two, INF - 3);
+        assert_eq!(in_four, INF - 7);
+        assert!(in_four > MATE);
+
+        // The solver scores a win the same way the search does.
+        let board = Board::from_notation("yyr2y1/ryy2r1/yrr2y1/ryy2r1/yrryyyr/ryrrryr").unwrap();
+        let solved = Minimax::new(&board, Color::Red, 1)
+            .best_move_with_stats()
+            .1
+            .score;
+        assert_eq!(solved, INF - 5);
+        assert_eq!(score("yyr2y1/ryy2r1/yrr2y1/ryy2r1/yrryyyr/ryrrryr"), solved);
+
+        let empty = Board::new();
+        let huge = Minimax::new(&empty, Color::Red, 2).with_evaluator(|_, _| INF);
+        assert!(
+            huge.analyze()
+                .iter()
+                .all(|&(_, eval)| eval == HEURISTIC_BOUND)
+        );
+    }
+
+    #[test]
+    fn solves_endgames_the_heuristic_misplays() {
+        let board = Board::from_notation("1r2ry1/yy2rr1/yyr1yr1/ryyryy1/yrrryry/yyryrrr").unwrap();
+        assert_eq!(board.piece_count(), 32);
+        let outcome = |file: usize| {
+            let mut board = board;
+            board.insert(file, Color::Red);
+            -Minimax::new(&board, Color::Yellow, 0).solve_exact()
+        };
+
+        let heuristic = Minimax::new(&board, Color::Red, 4).with_solver_threshold(None);
+        assert!(outcome(heuristic.best_move()) < 0);
+
+        let (file, stats) = Minimax::new(&board, Color::Red, 4).best_move_with_stats();
+        assert!(outcome(file) > 0);
+        assert!(stats.score > MATE);
+    }
+
+    #[test]
+    fn narrowest_windows_do_not_overflow() {
+        let board = Board::from_moves("4455").unwrap();
+        let engine = Minimax::new(&board, Color::Red, 4);
+        let search = engine.search();
+
+        for (alpha, beta) in [(-INF, -INF + 1), (INF - 1, INF), (0, 1), (-INF, INF)] {
+            for color in [Color::Red, Color::Yellow] {
+                let mut board = board;
+                let score = minimax(&mut board, color, 4, alpha, beta, &search);
+                assert!((-INF..=INF).contains(&score), "{}", score);
+                assert!((-INF..=INF).contains(&-score));
+            }
+        }
+
+        // A full board is a draw, whatever the window.
+        let mut full =
+            Board::from_notation("ryryryr/ryryryr/yryryry/yryryry/ryryryr/ryryryr").unwrap();
+        let score = minimax(&mut full, Color::Red, 2, -INF, -INF + 1, &search);
+        assert_eq!(score, 0);
+    }
+
+    #[test]
+    fn threat_ordering_searches_fewer_nodes() {
+        for moves in ["", "4453", "4444", "3345"] {
+            let board = Board::from_moves(moves).unwrap();
+            let color = match moves.len() % 2 {
+                0 => Color::Red,
+                _ => Color::Yellow,
+            };
+            let search = |ordering| {
+                let minimax = Minimax::new(&board, color, 6).with_ordering(ordering);
+                (minimax.analyze(), minimax.best_move_with_stats())
+            };
+
+            let (threats, (threats_best, threats_stats)) = search(MoveOrdering::Threats);
+            let (center, (center_best, center_stats)) = search(MoveOrdering::CenterOut);
+            // Root moves are searched with full windows, so ordering only changes the work.
+            assert_eq!(threats, center, "{}", moves);
+            assert_eq!(threats_best, center_best);
+            assert!(
+                threats_stats.nodes < center_stats.nodes,
+                "{}: {} vs {}",
+                moves,
+                threats_stats.nodes,
+                center_stats.nodes
+            );
+        }
+    }
+
+    #[test]
+    fn delays_a_forced_loss() {
+        // Yellow wins whatever Red does. Most moves lose in three plies and the fifth column in
+        // one, but the third column holds out for five.
+        let board = Board::from_notation("7/5r1/5yy/2r2rr/y1ry1yy/r1yr1yr").unwrap();
+        let minimax = Minimax::new(&board, Color::Red, 5);
+
+        let plies = (0..7)
+            .map(|file| minimax.plies_to_loss(file))
+            .collect::<Vec<_>>();
+        assert_eq!(plies, vec![3, 3, 5, 3, 1, 3, 3]);
+        assert_eq!(minimax.best_move_for(Objective::DelayLoss), 2);
+        assert_eq!(minimax.best_move_for(Objective::Win), minimax.best_move());
+
+        // Filling file 1 would leave Yellow one column fewer to choose from.
+        let board = Board::from_moves("11111").unwrap();
+        let minimax = Minimax::new(&board, Color::Yellow, 4);
+        let file = minimax.best_move_for(Objective::MaximizeComplexity);
+        assert_ne!(file, 0);
+        assert!(minimax.safe_moves().contains(&file));
+    }
+
+    #[test]
+    fn personality_changes_style() {
+        // Red can stack a third piece in file 6 or cap Yellow's pair in file 7.
+        let board = Board::from_notation("7/7/7/7/5ry/1ryyrry").unwrap();
+
+        let aggressive = Minimax::new(&board, Color::Red, 1)
+            .with_personality(Personality::aggressive())
+            .best_move();
+        let defensive = Minimax::new(&board, Color::Red, 1)
+            .with_personality(Personality::defensive())
+            .best_move();
+        assert_eq!(aggressive, 5);
+        assert_eq!(defensive, 6);
+
+        let biased = Personality {
+            column_bias: [0, 0, 0, 0, 0, 0, 1000],
+            ..Personality::default()
+        };
+        let minimax = Minimax::new(&board, Color::Red, 1).with_personality(biased);
+        assert_eq!(minimax.best_move(), 6);
+    }
+
+    #[test]
+    fn finds_safe_moves() {
+        // Red threatens to complete the bottom row, so Yellow must block.
+        let board = Board::from_moves("15253").unwrap();
+        assert_eq!(Minimax::new(&board, Color::Yellow, 4).safe_moves(), vec![3]);
+
+        let board = Board::new();
+        assert_eq!(
+            Minimax::new(&board, Color::Red, 6).safe_moves(),
+            vec![0, 1, 2, 3, 4, 5, 6]
+        );
+    }
+
+    #[test]
+    fn multi_pv_leads_with_best_move() {
+        let board = Board::from_notation("7/7/7/7/3y3/2rr3").unwrap();
+        let minimax = Minimax::new(&board, Color::Yellow, 3);
+
+        let lines = minimax.multi_pv(3);
+        let best = minimax.best_move();
+        let (_, best_eval, _) = minimax
+            .multi_pv(7)
+            .into_iter()
+            .find(|l| l.0 == best)
+            .unwrap();
+
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0].0, best);
+        assert_eq!(lines[0].1, best_eval);
+        assert!(lines.windows(2).all(|pair| pair[0].1 <= pair[1].1));
+        for (file, _, line) in &lines {
+            assert_eq!(line[0], (Color::Yellow, *file));
+            assert_eq!(line.len(), 4);
+        }
+    }
+
+    #[test]
+    fn multi_pv_stops_at_a_connect_n_win() {
+        // Red's open two on the bottom row wins next turn in Connect-3 whatever Yellow does.
+        let board = Board::from_notation("7/7/7/7/7/2r4").unwrap();
+        let minimax = Minimax::new(&board, Color::Red, 6).with_connect(3);
+
+        let (_, _, line) = minimax.multi_pv(1).remove(0);
+        let mut board = board;
+        for (i, &(color, file)) in line.iter().enumerate() {
+            assert!(!board.has_connect_n(color.other(), 3));
+            board.insert(file, color);
+            assert_eq!(board.has_connect_n(color, 3), i == line.len() - 1);
+        }
+        assert_eq!(line.len(), 3);
+    }
+
+    #[test]
+    fn stop_flag_returns_promptly() {
+        let board = Board::new();
+        let stop = Arc::new(AtomicBool::new(true));
+        let minimax = Minimax::new(&board, Color::Red, 40).with_stop(stop);
+
+        let start = Instant::now();
+        let file = minimax.best_move();
+
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert!(board.legal_files().contains(&file));
+    }
+
+    #[test]
+    fn time_limit_ends_search() {
+        let board = Board::new();
+        let minimax =
+            Minimax::new(&board, Color::Red, 40).with_time_limit(Duration::from_millis(100));
+
+        let start = Instant::now();
+        let file = minimax.best_move();
+
+        assert!(start.elapsed() < Duration::from_secs(2));
+        assert!(board.legal_files().contains(&file));
+    }
+
+    #[test]
+    fn stop_flag_interrupts_running_search() {
+        let board = Board::new();
+        let stop = Arc::new(AtomicBool::new(false));
+        let minimax = Minimax::new(&board, Color::Yellow, 40).with_stop(stop.clone());
+
+        let start = Instant::now();
+        let file = std::thread::scope(|s| {
+            let search = s.spawn(|| minimax.best_move());
+            std::thread::sleep(Duration::from_millis(50));
+            stop.store(true, Ordering::Relaxed);
+            search.join().unwrap()
+        });
+
+        assert!(start.elapsed() < Duration::from_secs(2));
+        assert!(board.legal_files().contains(&file));
+    }
+
+    /// Positions from Pascal Pons' solver benchmarks, as 1-indexed files played from Red, with
+    /// their published scores for the side to move in the same convention as `solve_exact`.
+    #[test]
+    fn agrees_with_known_solved_positions() {
+        let positions = [
+            ("2252576253462244111563365343671351441", -1),
+            ("7422341735647741166133573473242566", 1),
+            ("23163416124767223154467471272416755633", 0),
+            ("65214673556155731566316327373221417", -1),
+        ];
+
+        for (moves, expected) in positions {
+            let board = Board::from_moves(moves).unwrap();
+            let color = match moves.len() % 2 {
+                0 => Color::Red,
+                _ => Color::Yellow,
+            };
+            assert_eq!(
+                Minimax::new(&board, color, 0).solve_exact(),
+                expected,
+                "{}",
+                moves
+            );
+        }
+    }
+
+    /// Connect 4 is solved: the first player wins by opening in the center, and draws or loses
+    /// from every other file. Files 4 to 6 mirror 0 to 2. Even proving only who wins takes
+    /// minutes, so run it with `cargo test --release -- --ignored`.
+    #[test]
+    #[ignore = "solves the game from the first move"]
+    fn agrees_with_the_solved_opening() {
+        // A null window just below a draw proves whether Red wins, which is far cheaper than
+        // finding how soon. Yellow can't win with its first piece, as `negamax` requires.
+        let red_wins = |file: usize| {
+            let mut board = Board::new();
+            board.insert(file, Color::Red);
+            negamax(
+                &mut board,
+                Color::Yellow,
+                -1,
+                0,
+                &mut TranspositionTable::new(),
+            ) < 0
+        };
+        assert!(red_wins(3));
+        for file in 0..3 {
+            assert!(!red_wins(file), "file {}", file);
+        }
+    }
+}
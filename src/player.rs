@@ -0,0 +1,194 @@
+use rand::{Rng, SeedableRng, rngs::StdRng};
+
+use crate::{
+    board::{Board, Color, EvalWeights},
+    mcts::Mcts,
+    minimax::Minimax,
+};
+
+/// Anything that can choose a move, so engines can play each other.
+pub trait Player {
+    /// Chooses a legal file for `color` to play. The board always has a legal move.
+    fn choose_move(&mut self, board: &Board, color: Color) -> usize;
+}
+
+pub struct MinimaxPlayer {
+    pub depth: usize,
+    pub weights: EvalWeights,
+}
+
+impl MinimaxPlayer {
+    pub fn new(depth: usize) -> Self {
+        MinimaxPlayer {
+            depth,
+            weights: EvalWeights::default(),
+        }
+    }
+}
+
+impl Player for MinimaxPlayer {
+    fn choose_move(&mut self, board: &Board, color: Color) -> usize {
+        Minimax::new(board, color, self.depth)
+            .with_weights(self.weights)
+            .best_move()
+    }
+}
+
+pub struct MctsPlayer {
+    pub iterations: usize,
+    rng: StdRng,
+}
+
+impl MctsPlayer {
+    pub fn new(iterations: usize, seed: u64) -> Self {
+        MctsPlayer {
+            iterations,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl Player for MctsPlayer {
+    fn choose_move(&mut self, board: &Board, color: Color) -> usize {
+        Mcts::new(board, color, self.iterations)
+            .with_seed(self.rng.r#gen())
+            .best_move()
+    }
+}
+
+/// Plays uniformly random legal moves.
+pub struct RandomPlayer {
+    rng: StdRng,
+}
+
+impl RandomPlayer {
+    pub fn new(seed: u64) -> Self {
+        RandomPlayer {
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl Player for RandomPlayer {
+    fn choose_move(&mut self, board: &Board, _color: Color) -> usize {
+        let files = board.legal_files();
+        files[self.rng.gen_range(0..files.len())]
+    }
+}
+
+/// Wraps another player and, with probability `blunder_rate`, plays a random legal move
+/// instead of asking it. Makes for beatable opponents that still play plausibly.
+pub struct NoisyPlayer<P: Player> {
+    pub inner: P,
+    pub blunder_rate: f64,
+    rng: StdRng,
+}
+
+impl<P: Player> NoisyPlayer<P> {
+    pub fn new(inner: P, blunder_rate: f64, seed: u64) -> Self {
+        NoisyPlayer {
+            inner,
+            blunder_rate: blunder_rate.clamp(0.0, 1.0),
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl<P: Player> Player for NoisyPlayer<P> {
+    fn choose_move(&mut self, board: &Board, color: Color) -> usize {
+        match self.rng.gen_bool(self.blunder_rate) {
+            true => {
+                let files = board.legal_files();
+                files[self.rng.gen_range(0..files.len())]
+            }
+            false => self.inner.choose_move(board, color),
+        }
+    }
+}
+
+/// Plays a game from the empty board, Red first, returning the winner. The first
+/// `random_plies` moves are chosen at random so games between the same players differ.
+pub fn play_game(
+    red: &mut dyn Player,
+    yellow: &mut dyn Player,
+    random_plies: usize,
+    rng: &mut impl Rng,
+) -> Option<Color> {
+    let mut board = Board::new();
+    let mut color = Color::Red;
+
+    for ply in 0.. {
+        let files = board.legal_files();
+        if files.is_empty() {
+            break;
+        }
+
+        let file = match (ply < random_plies, color) {
+            (true, _) => files[rng.gen_range(0..files.len())],
+            (false, Color::Red) => red.choose_move(&board, color),
+            (false, Color::Yellow) => yellow.choose_move(&board, color),
+        };
+
+        board.insert(file, color);
+        if board.last_move_wins(file) {
+            return Some(color);
+        }
+        color = color.other();
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Always plays the leftmost legal file, counting how often it is asked.
+    struct CountingPlayer {
+        calls: usize,
+    }
+
+    impl Player for CountingPlayer {
+        fn choose_move(&mut self, board: &Board, _color: Color) -> usize {
+            self.calls += 1;
+            board.legal_files()[0]
+        }
+    }
+
+    #[test]
+    fn noisy_player_blunders_at_its_rate() {
+        let decisions = 10_000;
+        let mut noisy = NoisyPlayer::new(CountingPlayer { calls: 0 }, 0.2, 385);
+
+        for _ in 0..decisions {
+            let file = noisy.choose_move(&Board::new(), Color::Red);
+            assert!(file < 7);
+        }
+
+        let blunders = decisions - noisy.inner.calls;
+        let rate = blunders as f64 / decisions as f64;
+        assert!((rate - 0.2).abs() < 0.02, "blunder rate {}", rate);
+
+        let mut perfect = NoisyPlayer::new(CountingPlayer { calls: 0 }, 0.0, 385);
+        for _ in 0..100 {
+            assert_eq!(perfect.choose_move(&Board::new(), Color::Red), 0);
+        }
+        assert_eq!(perfect.inner.calls, 100);
+    }
+
+    #[test]
+    fn engine_beats_random_player() {
+        let mut rng = StdRng::seed_from_u64(359);
+        let mut engine = MinimaxPlayer::new(4);
+        let mut random = RandomPlayer::new(359);
+
+        assert_eq!(
+            play_game(&mut engine, &mut random, 0, &mut rng),
+            Some(Color::Red)
+        );
+        assert_eq!(
+            play_game(&mut random, &mut engine, 0, &mut rng),
+            Some(Color::Yellow)
+        );
+    }
+}
@@ -0,0 +1,212 @@
+use std::{
+    io::{self, BufRead, Write},
+    time::Duration,
+};
+
+use anyhow::{anyhow, bail};
+
+use crate::{
+    board::{Board, Color},
+    minimax::Minimax,
+};
+
+/// A line protocol for driving the engine from another program, loosely modeled on UCI:
+///
+/// ```text
+/// > position 7/7/7/7/7/3r3 yellow
+/// > go depth 8
+/// < info depth 8 score -3 nodes 12345
+/// < bestmove 4
+/// ```
+///
+/// Positions use `Board::from_notation_strict` (or `startpos`) followed by the side to move.
+/// Searches are `go depth N` or `go time MS`. Columns are 1-7 and scores are positive favoring
+/// Red, as everywhere else. `isready` answers `readyok` and `quit` ends the session. Anything else
+/// gets an `info string` explaining what went wrong.
+pub struct Protocol {
+    board: Board,
+    to_move: Color,
+}
+
+impl Default for Protocol {
+    fn default() -> Self {
+        Protocol::new()
+    }
+}
+
+impl Protocol {
+    pub fn new() -> Self {
+        Protocol {
+            board: Board::new(),
+            to_move: Color::Red,
+        }
+    }
+
+    /// Answers commands from `input` until it ends or a `quit`.
+    pub fn run(&mut self, input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+        for line in input.lines() {
+            let line = line?;
+            match line.trim() {
+                "" => continue,
+                "quit" => break,
+                command => {
+                    for response in self.respond(command) {
+                        writeln!(output, "{}", response)?;
+                    }
+                    output.flush()?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The lines to send back for one command.
+    pub fn respond(&mut self, command: &str) -> Vec<String> {
+        let (name, args) = command.split_once(' ').unwrap_or((command, ""));
+        let result = match name {
+            "isready" => Ok(vec!["readyok".to_string()]),
+            "position" => self.position(args).map(|()| Vec::new()),
+            "go" => self.go(args),
+            _ => Err(anyhow!("unknown command '{}'", name)),
+        };
+
+        result.unwrap_or_else(|e| vec![format!("info string {}", e)])
+    }
+
+    fn position(&mut self, args: &str) -> Result<(), anyhow::Error> {
+        let (position, to_move) = match args.split_whitespace().collect::<Vec<_>>()[..] {
+            [position, to_move] => (position, to_move),
+            _ => bail!("usage: position <notation|startpos> <red|yellow>"),
+        };
+
+        let board = match position {
+            "startpos" => Board::new(),
+            notation => Board::from_notation_strict(notation)?,
+        };
+        self.to_move = match to_move {
+            "red" | "r" => Color::Red,
+            "yellow" | "y" => Color::Yellow,
+            _ => bail!("side to move must be red or yellow, not '{}'", to_move),
+        };
+        self.board = board;
+
+        Ok(())
+    }
+
+    fn go(&mut self, args: &str) -> Result<Vec<String>, anyhow::Error> {
+        let (limit, value) = match args.split_whitespace().collect::<Vec<_>>()[..] {
+            [limit, value] => (limit, value.parse::<u64>()?),
+            _ => bail!("usage: go depth <plies> | go time <ms>"),
+        };
+
+        if self.board.legal_files().is_empty()
+            || self.board.has_connect_4(Color::Red)
+            || self.board.has_connect_4(Color::Yellow)
+        {
+            return Ok(vec!["bestmove none".to_string()]);
+        }
+
+        let minimax = match limit {
+            "depth" => Minimax::new(&self.board, self.to_move, value as usize),
+            // Iterative deepening runs until the deadline, or until the board is searched out.
+            "time" => Minimax::new(&self.board, self.to_move, 42 - self.board.piece_count())
+                .with_time_limit(Duration::from_millis(value)),
+            _ => bail!("usage: go depth <plies> | go time <ms>"),
+        };
+        let (file, stats) = minimax.best_move_with_stats();
+
+        Ok(vec![
+            format!(
+                "info depth {} score {} nodes {}",
+                stats.depth, stats.score, stats.nodes
+            ),
+            format!("bestmove {}", file + 1),
+        ])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+
+    use super::*;
+
+    fn session(input: &str) -> Vec<String> {
+        let mut output = Vec::new();
+        Protocol::new()
+            .run(Cursor::new(input), &mut output)
+            .unwrap();
+        String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(str::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn answers_scripted_session() {
+        let output = session(
+            "isready\n\
+             position 7/7/7/7/yy5/rrr4 red\n\
+             go depth 2\n\
+             position 7/7/7/7/1y5/1rrr2y yellow\n\
+             go depth 3\n\
+             quit\n\
+             go depth 3\n",
+        );
+
+        assert_eq!(output.len(), 5, "{:?}", output);
+        assert_eq!(output[0], "readyok");
+        assert!(
+            output[1].starts_with("info depth 0 score "),
+            "{}",
+            output[1]
+        );
+        assert_eq!(output[2], "bestmove 4");
+        assert!(output[3].starts_with("info depth "), "{}", output[3]);
+        assert!(output[3].contains(" nodes "), "{}", output[3]);
+        assert!(["bestmove 1", "bestmove 5"].contains(&output[4].as_str()));
+    }
+
+    #[test]
+    fn reports_bad_commands() {
+        let output = session(
+            "position 7/7/7/7/7/6x red\n\
+             position startpos blue\n\
+             go depth\n\
+             dance\n\
+             position 7/7/7/7/yyy4/rrrr3 yellow\n\
+             go depth 4\n",
+        );
+
+        assert_eq!(output.len(), 5, "{:?}", output);
+        assert!(
+            output[..4]
+                .iter()
+                .all(|line| line.starts_with("info string "))
+        );
+        assert_eq!(output[4], "bestmove none");
+    }
+
+    #[test]
+    fn rejects_illegal_positions() {
+        // A red piece floats above an empty file; the search carries on from the start position.
+        let output = session("position 7/7/7/7/r6/7 yellow\ngo depth 1\n");
+
+        assert_eq!(output.len(), 3, "{:?}", output);
+        assert_eq!(
+            output[0],
+            "info string pieces float above empty cells: 0x10000000"
+        );
+        assert!(output[2].starts_with("bestmove "));
+    }
+
+    #[test]
+    fn searches_for_a_time() {
+        let output = session("position startpos red\ngo time 50\n");
+
+        assert_eq!(output.len(), 2, "{:?}", output);
+        assert!(output[1].starts_with("bestmove "));
+    }
+}
@@ -1,18 +1,147 @@
-use std::io::{BufRead, BufReader, Stdin};
+use std::{
+    cell::Cell,
+    fs::OpenOptions,
+    io::{BufRead, BufReader, IsTerminal, Write},
+    path::PathBuf,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+    },
+    thread,
+    time::{Duration, Instant},
+};
 
 use colored::Colorize;
-use rand::random;
+use rand::{Rng, SeedableRng, random, rngs::StdRng};
+use rustyline::{DefaultEditor, error::ReadlineError};
 
+#[cfg(feature = "save")]
+use crate::save::SavedGame;
 use crate::{
-    board::{Board, Color},
-    minimax::Minimax,
+    board::{Board, Color, EvalWeights, Phase},
+    book::Book,
+    coach::{MoveQuality, review_move},
+    mcts::Mcts,
+    minimax::{INF, MATE, Minimax, SearchStats},
+    player::{MinimaxPlayer, Player},
+    share,
 };
 
+/// A position under study, apart from the game: moves alternate from the loaded side to move,
+/// can be taken back freely, and a win ends nothing.
+struct Analysis {
+    board: Board,
+    turn: Color,
+    moves: Vec<usize>,
+}
+
+/// A chess clock: each side's budget counts down across the whole game.
+#[derive(Debug, Clone, Copy)]
+struct Clock {
+    budget: Duration,
+    red: Duration,
+    yellow: Duration,
+}
+
+impl Clock {
+    fn new(budget: Duration) -> Self {
+        Clock {
+            budget,
+            red: budget,
+            yellow: budget,
+        }
+    }
+
+    fn remaining(&self, color: Color) -> Duration {
+        match color {
+            Color::Red => self.red,
+            Color::Yellow => self.yellow,
+        }
+    }
+
+    /// Deducts `elapsed` from `color`'s clock, returning true if it has run out.
+    fn charge(&mut self, color: Color, elapsed: Duration) -> bool {
+        let remaining = match color {
+            Color::Red => &mut self.red,
+            Color::Yellow => &mut self.yellow,
+        };
+        *remaining = remaining.saturating_sub(elapsed);
+        remaining.is_zero()
+    }
+}
+
+impl std::fmt::Display for Clock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let format = |d: Duration| format!("{}:{:02}", d.as_secs() / 60, d.as_secs() % 60);
+        write!(
+            f,
+            "Clock: {} {} | {} {}",
+            Color::Red,
+            format(self.red),
+            Color::Yellow,
+            format(self.yellow)
+        )
+    }
+}
+
+/// Coaching reviews moves until this many pieces are on the board.
+const OPENING_PLIES: usize = 10;
+
+/// Search depth for coaching reviews, kept shallow so feedback is instant.
+const COACH_DEPTH: usize = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Engine {
+    Minimax,
+    Mcts,
+}
+
 pub struct Repl {
     board: Board,
     turn: Color,
     player: Color,
-    reader: BufReader<Stdin>,
+    history: Vec<(Color, usize)>,
+    depth: usize,
+    engine: Engine,
+    clock: Option<Clock>,
+    /// Shows the board rotated half a turn, with file input following the visual columns.
+    flipped: bool,
+    /// Renders without color or escape codes.
+    plain: bool,
+    input: Receiver<String>,
+    /// Asks the input thread for the next line. A terminal is only in the editor's raw mode
+    /// while a line is wanted, so the game can exit without leaving it that way.
+    wants_line: Sender<()>,
+    waiting: Cell<bool>,
+    interruptible: bool,
+    /// Appends a line per computer move with the position and search stats, for post-mortems.
+    trace: Option<PathBuf>,
+    /// Compares each opening move of the player's with the book or a shallow search.
+    coach: bool,
+    book: Book,
+    /// Handicaps for casual play: the player always moves first, and the engine searches no
+    /// deeper than the cap.
+    human_first: bool,
+    depth_cap: Option<usize>,
+    /// The line length that wins: 3, 4 or 5.
+    connect: usize,
+    /// Pop Out: on their turn a player may instead remove one of their own pieces from the
+    /// bottom of a column. Only the player pops; the engine still just drops.
+    pop_out: bool,
+    /// Each position reached this game with the side to move, so repetitions can be drawn.
+    positions: Vec<(u64, Color)>,
+    /// Prints an evaluation bar before each move, searched `analysis_depth` plies deep so it
+    /// stays quick whatever the engine's depth.
+    eval_bar: bool,
+    analysis_depth: usize,
+    seed: Option<u64>,
+    /// Two people share the keyboard, taking both colors, and the computer never moves.
+    hotseat: bool,
+    /// Deepens until `adaptive_budget` runs out instead of stopping at `depth`.
+    adaptive: bool,
+    /// How long `auto_play` shows each position before the next move.
+    auto_pause: Duration,
 }
 
 impl Default for Repl {
@@ -22,9 +151,50 @@ impl Default for Repl {
 }
 
 impl Repl {
+    /// Reads commands from stdin, with line editing and history when it is a terminal.
     pub fn new() -> Self {
         let stdin = std::io::stdin();
-        let reader = BufReader::new(stdin);
+        let editor = stdin
+            .is_terminal()
+            .then(DefaultEditor::new)
+            .and_then(Result::ok);
+        let mut repl = Repl::with_input(editor, BufReader::new(stdin));
+        if !std::io::stdout().is_terminal() {
+            repl.set_plain(true);
+        }
+        repl
+    }
+
+    /// Reads commands through `editor` if there is one, where Enter also interrupts the
+    /// computer's search, and otherwise as plain lines from `reader`.
+    fn with_input(editor: Option<DefaultEditor>, reader: impl BufRead + Send + 'static) -> Self {
+        match editor {
+            Some(mut editor) => {
+                let mut repl = Repl::with_lines(std::iter::from_fn(move || edit_line(&mut editor)));
+                repl.interruptible = true;
+                repl
+            }
+            None => Repl::with_reader(reader),
+        }
+    }
+
+    /// Reads commands from `reader` on a background thread. Scripted input can't interrupt the
+    /// computer's search, since every queued line would be taken as a keypress.
+    pub fn with_reader(reader: impl BufRead + Send + 'static) -> Self {
+        Repl::with_lines(reader.lines().map_while(Result::ok))
+    }
+
+    fn with_lines(mut lines: impl Iterator<Item = String> + Send + 'static) -> Self {
+        let (sender, input) = mpsc::channel();
+        let (wants_line, requests) = mpsc::channel::<()>();
+        thread::spawn(move || {
+            for () in requests {
+                let Some(line) = lines.next() else { break };
+                if sender.send(line + "\n").is_err() {
+                    break;
+                }
+            }
+        });
 
         let turn = match random::<bool>() {
             true => Color::Red,
@@ -35,63 +205,749 @@ impl Repl {
             board: Board::new(),
             turn,
             player: Color::Yellow,
-            reader,
+            history: Vec::new(),
+            depth: 12,
+            engine: Engine::Minimax,
+            clock: None,
+            flipped: false,
+            plain: false,
+            input,
+            wants_line,
+            waiting: Cell::new(false),
+            interruptible: false,
+            trace: None,
+            coach: false,
+            book: Book::standard(),
+            human_first: false,
+            depth_cap: None,
+            connect: 4,
+            pop_out: false,
+            positions: Vec::new(),
+            eval_bar: false,
+            analysis_depth: 4,
+            seed: None,
+            hotseat: false,
+            adaptive: false,
+            auto_pause: Duration::from_millis(500),
         }
     }
 
+    /// Logs each computer move to `path`, appending if it already exists.
+    pub fn with_trace(mut self, path: impl Into<PathBuf>) -> Self {
+        self.trace = Some(path.into());
+        self
+    }
+
+    /// Always gives the player the first move instead of flipping a coin.
+    pub fn with_human_first(mut self) -> Self {
+        self.human_first = true;
+        self
+    }
+
+    /// Plays Connect-N on the same 7x6 board, where `n` (3, 4 or 5) in a row wins.
+    pub fn with_connect(mut self, n: usize) -> Self {
+        self.connect = n;
+        self
+    }
+
+    /// Renders with ASCII letters and no color, for screen readers and monochrome terminals.
+    pub fn with_plain(mut self) -> Self {
+        self.set_plain(true);
+        self
+    }
+
+    /// Sets how deep the engine searches.
+    pub fn with_depth(mut self, depth: usize) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    /// Plays Pop Out, where a player may pop one of their own pieces from the bottom of a column.
+    pub fn with_pop_out(mut self) -> Self {
+        self.pop_out = true;
+        self
+    }
+
+    /// Makes the coin toss for the first move, and the Mcts engine's rollouts, reproducible.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self.turn = match StdRng::seed_from_u64(seed).r#gen::<bool>() {
+            true => Color::Red,
+            false => Color::Yellow,
+        };
+        self
+    }
+
+    /// Lets two people play each other, entering moves for both colors in turn.
+    pub fn with_hotseat(mut self) -> Self {
+        self.hotseat = true;
+        self
+    }
+
+    /// Searches as deep as `adaptive_budget` allows each move, rather than to a fixed depth.
+    pub fn with_adaptive(mut self) -> Self {
+        self.adaptive = true;
+        self
+    }
+
+    /// Caps the engine's search depth.
+    pub fn with_depth_cap(mut self, depth: usize) -> Self {
+        self.depth_cap = Some(depth);
+        self
+    }
+
     pub fn start(mut self) {
-        self.choose_color();
+        if !self.hotseat {
+            self.choose_color();
+        }
+        while self.step() {}
+    }
 
-        loop {
-            println!("{}", self.board);
-            println!("Turn: {}", self.turn);
-
-            match self.turn == self.player {
-                true => {
-                    println!("Enter file (1-7) to play: ");
-
-                    let buffer = self.read_input();
-
-                    match buffer.as_str().trim_end() {
-                        "newgame" | "n" => self.board = Board::new(),
-                        "quit" | "q" => break,
-                        file => {
-                            if let Ok(file) = file.trim_ascii().parse::<usize>() {
-                                if !(1..=7).contains(&file) {
-                                    println!("Bad file. Please enter 1-7.");
-                                    continue;
+    /// Shows the position and plays one turn: a command or move from the player, or the
+    /// computer's move. Returns false once the player quits.
+    fn step(&mut self) -> bool {
+        println!("{}", self.status_line());
+        println!("{}", self.render_board());
+        let full = self.board.full_columns();
+        if !full.is_empty() {
+            let columns = full
+                .iter()
+                .map(|&file| (self.visual_file(file) + 1).to_string())
+                .collect::<Vec<_>>();
+            println!("Full: {}", columns.join(", "));
+        }
+        if let Some(clock) = &self.clock {
+            println!("{}", clock);
+        }
+        if let Some(bar) = self.eval_bar.then(|| self.eval_bar()).flatten() {
+            println!("{}", bar);
+        }
+
+        // In hotseat the player is whoever is to move, so every turn is read from the input.
+        if self.hotseat {
+            self.player = self.turn;
+        }
+
+        match self.turn == self.player {
+            true => {
+                match self.hotseat {
+                    true => println!(
+                        "{} to play. Enter file (1-7 or A-G): ",
+                        self.color_label(self.turn)
+                    ),
+                    false => println!("Enter file (1-7 or A-G) to play: "),
+                }
+
+                let start = Instant::now();
+                let buffer = self.read_input();
+                if let Some(winner) = self.charge_clock(self.player, start.elapsed()) {
+                    self.end_game(Some(winner));
+                    return true;
+                }
+
+                match buffer.as_str().trim_end() {
+                    "newgame" | "n" => self.new_game(),
+                    "quit" | "q" => return false,
+                    "flip" => self.flipped = !self.flipped,
+                    "plain" => self.set_plain(!self.plain),
+                    "engine minimax" => self.engine = Engine::Minimax,
+                    "engine mcts" => self.engine = Engine::Mcts,
+                    "trace off" => self.trace = None,
+                    "handicap first" => self.human_first = true,
+                    "handicap off" => {
+                        self.human_first = false;
+                        self.depth_cap = None;
+                    }
+                    cmd if cmd.starts_with("handicap depth") => {
+                        match cmd["handicap depth".len()..].trim().parse::<usize>() {
+                            Ok(depth) => self.depth_cap = Some(depth),
+                            Err(_) => println!("Usage: handicap depth <plies>"),
+                        }
+                    }
+                    cmd if cmd.starts_with("whatif ") => {
+                        match self.parse_file(&self.board, &cmd["whatif".len()..]) {
+                            Ok(file) => self.print_what_if(file),
+                            Err(e) => println!("{}", e),
+                        }
+                    }
+                    cmd if cmd.starts_with("connect") => {
+                        match cmd["connect".len()..].trim().parse::<usize>() {
+                            Ok(n @ 3..=5) => {
+                                self.connect = n;
+                                self.new_game();
+                                println!("New game of Connect-{}.", n);
+                            }
+                            _ => println!("Usage: connect <3-5>"),
+                        }
+                    }
+                    "popout on" | "popout off" => {
+                        self.pop_out = buffer.trim_end() == "popout on";
+                        self.new_game();
+                        match self.pop_out {
+                            true => println!("New game of Pop Out. Type 'pop <col>' to pop."),
+                            false => println!("New game."),
+                        }
+                    }
+                    cmd if cmd.starts_with("pop ") => {
+                        match self.parse_column(&cmd["pop".len()..]) {
+                            Ok(file) => {
+                                if let Err(e) = self.pop_file(file) {
+                                    println!("{}", e);
                                 }
-                                self.insert_file(file - 1);
-                            } else {
-                                println!("Unknown input.");
                             }
+                            Err(e) => println!("{}", e),
+                        }
+                    }
+                    "eval on" => self.eval_bar = true,
+                    "eval off" => self.eval_bar = false,
+                    cmd if cmd.starts_with("eval depth") => {
+                        match cmd["eval depth".len()..].trim().parse::<usize>() {
+                            Ok(depth) => self.analysis_depth = depth,
+                            Err(_) => println!("Usage: eval depth <plies>"),
+                        }
+                    }
+                    "undo" | "u" => {
+                        if let Err(e) = self.undo() {
+                            println!("{}", e);
+                        }
+                    }
+                    cmd if cmd == "auto" || cmd.starts_with("auto ") => {
+                        let depths = cmd["auto".len()..]
+                            .split_whitespace()
+                            .map(str::parse::<usize>)
+                            .collect::<Result<Vec<_>, _>>();
+                        match depths.as_deref() {
+                            Ok([]) => self.auto_play(self.depth, self.depth),
+                            Ok(&[depth]) => self.auto_play(depth, depth),
+                            Ok(&[red, yellow]) => self.auto_play(red, yellow),
+                            _ => println!("Usage: auto [depth] or auto <red depth> <yellow depth>"),
+                        }
+                    }
+                    "adaptive on" => self.adaptive = true,
+                    "adaptive off" => self.adaptive = false,
+                    "coach on" => self.coach = true,
+                    "coach off" => self.coach = false,
+                    cmd if cmd.starts_with("trace ") => {
+                        self.trace = Some(PathBuf::from(cmd["trace".len()..].trim()))
+                    }
+                    cmd if cmd.starts_with("clock") => match cmd["clock".len()..].trim() {
+                        "" => self.clock = Some(Clock::new(Duration::from_secs(5 * 60))),
+                        minutes => match minutes.parse::<u64>() {
+                            Ok(minutes) => {
+                                self.clock = Some(Clock::new(Duration::from_secs(minutes * 60)))
+                            }
+                            Err(_) => println!("Usage: clock [minutes]"),
+                        },
+                    },
+                    #[cfg(feature = "save")]
+                    cmd if cmd.starts_with("save ") => {
+                        // Pops aren't in the history, so it can't be checked against the board.
+                        let history = match self.pop_out {
+                            true => Vec::new(),
+                            false => self.history.clone(),
+                        };
+                        let game =
+                            SavedGame::new(self.board, self.turn, self.player, history, self.depth);
+                        match game.save(cmd["save".len()..].trim()) {
+                            Ok(()) => println!("Game saved."),
+                            Err(e) => println!("Could not save game: {}", e),
                         }
                     }
+                    "share" => println!("Share code: {}", share::encode(&self.board)),
+                    "explain" => match self.explain() {
+                        Some(explanation) => println!("{}", explanation),
+                        None => println!("The computer hasn't moved yet."),
+                    },
+                    cmd if cmd.starts_with("load ") => {
+                        if let Err(e) = self.load(cmd["load".len()..].trim()) {
+                            println!("Could not load game: {}", e);
+                        }
+                    }
+                    cmd if cmd.starts_with("analyze ") => {
+                        if let Err(e) = self.analyze(cmd["analyze".len()..].trim()) {
+                            println!("Could not analyze position: {}", e);
+                        }
+                    }
+                    cmd if cmd.starts_with("setup") => {
+                        if let Err(e) = self.setup(&cmd["setup".len()..]) {
+                            println!("Invalid position: {}", e);
+                        }
+                    }
+                    input => match self.parse_file(&self.board, input) {
+                        Ok(file) if !self.confirm_move(file) => {}
+                        Ok(file) => {
+                            if let Some(review) = self.coaching(file) {
+                                println!("{}", review);
+                            }
+                            self.insert_file(file);
+                        }
+                        Err(e) => println!("{}", e),
+                    },
                 }
-                false => {
-                    println!("Computer is thinking...");
-                    let minimax = Minimax::new(&self.board, self.player.other(), 12);
-                    let file = minimax.best_move();
-                    self.insert_file(file);
+            }
+            false => {
+                let start = Instant::now();
+                let file = self.think();
+                if let Some(winner) = self.charge_clock(self.player.other(), start.elapsed()) {
+                    self.end_game(Some(winner));
+                    return true;
                 }
+                self.insert_file(file);
             }
         }
+
+        true
+    }
+
+    /// Switches plain rendering, and color everywhere else, on or off. Off leaves color to the
+    /// `colored` crate's usual terminal and environment checks.
+    fn set_plain(&mut self, plain: bool) {
+        self.plain = plain;
+        match plain {
+            true => colored::control::set_override(false),
+            false => colored::control::unset_override(),
+        }
+    }
+
+    fn render_board(&self) -> String {
+        self.render_position(&self.board)
+    }
+
+    fn render_position(&self, board: &Board) -> String {
+        match self.plain {
+            true => board.render_plain(self.flipped),
+            false => board.render(self.flipped),
+        }
+    }
+
+    /// A one-line summary of the game, e.g. "Move 14 | Turn: R | R threatens col 2 | Y threatens
+    /// none", where threats are columns the side could win in with its next drop.
+    fn status_line(&self) -> String {
+        let threats = |color: Color| {
+            let mut columns = self
+                .board
+                .threat_columns(color)
+                .iter()
+                .map(|&file| self.visual_file(file) + 1)
+                .collect::<Vec<_>>();
+            columns.sort_unstable();
+
+            let list = columns
+                .iter()
+                .map(|column| column.to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            let name = self.color_label(color);
+            match columns.len() {
+                0 => format!("{} threatens none", name),
+                1 => format!("{} threatens col {}", name, list),
+                _ => format!("{} threatens cols {}", name, list),
+            }
+        };
+
+        format!(
+            "Move {} | Turn: {} | {} | {}",
+            self.board.piece_count() + 1,
+            self.color_label(self.turn),
+            threats(Color::Red),
+            threats(Color::Yellow)
+        )
+    }
+
+    fn color_label(&self, color: Color) -> String {
+        match (self.plain, color) {
+            (true, Color::Red) => "R".to_string(),
+            (true, Color::Yellow) => "Y".to_string(),
+            (false, color) => color.to_string(),
+        }
+    }
+
+    /// Parses a column as typed, either `1`-`7` or `a`-`g` in either case, into a file of `board`
+    /// that isn't full.
+    fn parse_file(&self, board: &Board, input: &str) -> Result<usize, &'static str> {
+        let file = self.parse_column(input)?;
+        match board.legal_files().contains(&file) {
+            true => Ok(file),
+            false => Err("That column is full. Please choose another."),
+        }
+    }
+
+    /// Like `parse_file`, accepting full columns too.
+    fn parse_column(&self, input: &str) -> Result<usize, &'static str> {
+        let input = input.trim_ascii();
+        let column = match input.parse::<usize>() {
+            Ok(column) => column,
+            Err(_) => match input.as_bytes() {
+                [letter] if letter.is_ascii_alphabetic() => {
+                    (letter.to_ascii_lowercase() - b'a') as usize + 1
+                }
+                _ => return Err("Unknown input."),
+            },
+        };
+
+        if !(1..=7).contains(&column) {
+            return Err("Bad file. Please enter 1-7 or A-G.");
+        }
+
+        Ok(self.visual_file(column - 1))
+    }
+
+    /// Maps a column as seen on screen to a board file.
+    fn visual_file(&self, column: usize) -> usize {
+        match self.flipped {
+            true => Board::mirror_file(column),
+            false => column,
+        }
     }
 
     fn choose_color(&mut self) {
         println!("Choose your color ({}/{}): ", "Y".yellow(), "R".red());
+        self.player = self.read_color();
+        if self.human_first {
+            self.turn = self.player;
+        }
+    }
+
+    /// Replaces the current board with a position given either in notation or as a sequence of
+    /// files played alternately from Red, then asks whose turn it is.
+    fn setup(&mut self, args: &str) -> Result<(), anyhow::Error> {
+        let args = args.trim();
+        let board = match args.chars().all(|c| c.is_ascii_digit()) && !args.is_empty() {
+            true => Board::from_moves(args)?,
+            false => Board::from_notation_strict(args)?,
+        };
+        self.set_position(board);
+
+        Ok(())
+    }
+
+    /// Loads a saved game file or, if there is no such file, a code from the `share` command.
+    fn load(&mut self, arg: &str) -> Result<(), anyhow::Error> {
+        #[cfg(feature = "save")]
+        if std::path::Path::new(arg).exists() {
+            self.resume(SavedGame::load(arg)?);
+            return Ok(());
+        }
+        self.set_position(share::decode(arg)?);
+
+        Ok(())
+    }
+
+    /// Studies a share code or `Board::to_compact` number in its own loop until "done", leaving
+    /// the game as it was.
+    fn analyze(&mut self, code: &str) -> Result<(), anyhow::Error> {
+        let mut analysis = self.start_analysis(code)?;
+        println!(
+            "Analyzing. Play moves, 'whatif <file>', 'undo', or 'done' to return to the game."
+        );
 
         loop {
+            println!("{}", self.render_position(&analysis.board));
             let buffer = self.read_input();
-            match buffer.as_str().trim_end() {
-                c if c.starts_with('y') || c.starts_with('Y') => {
-                    self.player = Color::Yellow;
-                    break;
+            // An empty string means the input has ended.
+            if buffer.is_empty() || !self.analysis_command(&mut analysis, buffer.trim_end()) {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn start_analysis(&mut self, code: &str) -> Result<Analysis, anyhow::Error> {
+        let board = match code.parse::<u64>() {
+            Ok(compact) => Board::from_compact(compact)?,
+            Err(_) => share::decode(code)?,
+        };
+
+        println!("{}", self.render_position(&board));
+        println!("Whose turn is it ({}/{})? ", "Y".yellow(), "R".red());
+        Ok(Analysis {
+            board,
+            turn: self.read_color(),
+            moves: Vec::new(),
+        })
+    }
+
+    /// Runs one command in analysis, returning false once the player is done.
+    fn analysis_command(&self, analysis: &mut Analysis, input: &str) -> bool {
+        let over = [Color::Red, Color::Yellow]
+            .iter()
+            .any(|&color| analysis.board.has_connect_n(color, self.connect));
+
+        match input {
+            "done" | "quit" | "q" => return false,
+            "undo" => match analysis.moves.pop() {
+                Some(file) => {
+                    let _ = analysis.board.remove(file);
+                    analysis.turn = analysis.turn.other();
                 }
-                c if c.starts_with('r') || c.starts_with('R') => {
-                    self.player = Color::Red;
-                    break;
+                None => println!("Nothing to undo."),
+            },
+            cmd if cmd.starts_with("whatif ") => {
+                match self.parse_file(&analysis.board, cmd["whatif".len()..].trim()) {
+                    Ok(_) if over => {
+                        println!("The game is over here. Type 'undo' to keep exploring.")
+                    }
+                    Ok(file) => match self.reply_to(analysis.board, analysis.turn, file) {
+                        Some((reply, stats)) => println!(
+                            "After {}, the engine replies {} (eval {:+}).",
+                            self.visual_file(file) + 1,
+                            self.visual_file(reply) + 1,
+                            stats.score
+                        ),
+                        None => println!("Playing {} ends the game.", self.visual_file(file) + 1),
+                    },
+                    Err(e) => println!("{}", e),
                 }
+            }
+            input => match self.parse_file(&analysis.board, input) {
+                Ok(_) if over => println!("The game is over here. Type 'undo' to keep exploring."),
+                Ok(file) => match analysis.board.try_move(file, analysis.turn) {
+                    Ok(_) => {
+                        analysis.moves.push(file);
+                        if analysis.board.has_connect_n(analysis.turn, self.connect) {
+                            println!(
+                                "{} connects {}.",
+                                self.color_label(analysis.turn),
+                                self.connect
+                            );
+                        }
+                        analysis.turn = analysis.turn.other();
+                    }
+                    Err(e) => println!("{}", e),
+                },
+                Err(e) => println!("{}", e),
+            },
+        }
+
+        true
+    }
+
+    /// Replaces the board without any history, then asks whose turn it is.
+    fn set_position(&mut self, board: Board) {
+        self.board = board;
+        self.history.clear();
+        self.positions.clear();
+
+        println!("{}", self.render_board());
+        println!("Whose turn is it ({}/{})? ", "Y".yellow(), "R".red());
+        self.turn = self.read_color();
+    }
+
+    #[cfg(feature = "save")]
+    fn resume(&mut self, game: SavedGame) {
+        self.board = game.board;
+        self.turn = game.turn;
+        self.player = game.player;
+        self.history = game.history;
+        self.depth = game.depth;
+        self.positions.clear();
+    }
+
+    fn new_game(&mut self) {
+        self.board = Board::new();
+        self.history.clear();
+        self.positions.clear();
+        if self.human_first {
+            self.turn = self.player;
+        }
+        if let Some(clock) = &mut self.clock {
+            *clock = Clock::new(clock.budget);
+        }
+    }
+
+    /// The computer's best reply, with its search stats, if the player dropped in `file`. None
+    /// when that drop ends the game. The live board is left alone.
+    fn what_if(&self, file: usize) -> Option<(usize, SearchStats)> {
+        self.reply_to(self.board, self.player, file)
+    }
+
+    /// The engine's best reply if `color` dropped in `file` on `board`, or None when that drop
+    /// ends the game.
+    fn reply_to(
+        &self,
+        mut board: Board,
+        color: Color,
+        file: usize,
+    ) -> Option<(usize, SearchStats)> {
+        board.insert(file, color);
+        if board.has_connect_n(color, self.connect) || board.legal_files().is_empty() {
+            return None;
+        }
+
+        let minimax =
+            Minimax::new(&board, color.other(), self.engine_depth()).with_connect(self.connect);
+        Some(minimax.best_move_with_stats())
+    }
+
+    fn print_what_if(&self, file: usize) {
+        let column = self.visual_file(file) + 1;
+        match self.what_if(file) {
+            Some((reply, stats)) => println!(
+                "If you play {}, the computer replies {} (eval {:+}).",
+                column,
+                self.visual_file(reply) + 1,
+                stats.score
+            ),
+            None => println!("Playing {} ends the game.", column),
+        }
+    }
+
+    /// The best move's score for the side to move, as a bar and a number, e.g.
+    /// "[---|+++++++] Red +23". Each cell of the bar is worth 10. None once the game is over.
+    fn eval_bar(&self) -> Option<String> {
+        if [Color::Red, Color::Yellow]
+            .iter()
+            .any(|&color| self.board.has_connect_n(color, self.connect))
+        {
+            return None;
+        }
+
+        let (_, score) = *Minimax::new(&self.board, self.turn, self.analysis_depth)
+            .with_connect(self.connect)
+            .analyze()
+            .first()?;
+        let score = match self.turn {
+            Color::Red => score,
+            Color::Yellow => -score,
+        };
+
+        let plus = (5 + score / 10).clamp(0, 10) as usize;
+        Some(format!(
+            "[{}|{}] {} {}",
+            "-".repeat(10 - plus),
+            "+".repeat(plus),
+            self.color_label(self.turn),
+            format_score(score)
+        ))
+    }
+
+    /// Why the computer made its last move: its evaluation against the runner-up, whether it won
+    /// or blocked a win outright, and the line it expects, all searched `analysis_depth` plies
+    /// from the position before it. None if the computer hasn't moved since the game began.
+    fn explain(&self) -> Option<String> {
+        let &(color, file) = self.history.last()?;
+        if color == self.player && !self.hotseat {
+            return None;
+        }
+        let mut before = self.board;
+        let _ = before.remove(file);
+
+        let evaluations = Minimax::new(&before, color, self.analysis_depth)
+            .with_connect(self.connect)
+            .multi_pv(7);
+        let for_mover = |eval: i32| match color {
+            Color::Red => eval,
+            Color::Yellow => -eval,
+        };
+        let column = |file: usize| self.visual_file(file) + 1;
+
+        let (_, eval, line) = evaluations.iter().find(|(f, _, _)| *f == file)?;
+        let mut lines = vec![format!(
+            "{} played {} (eval {}).",
+            self.color_label(color),
+            column(file),
+            format_score(for_mover(*eval))
+        )];
+
+        if before.find_immediate_win_n(color, self.connect) == Some(file) {
+            lines.push("It wins on the spot.".to_string());
+        } else if for_mover(*eval) > MATE {
+            lines.push(format!(
+                "It forces a win in {}.",
+                (INF - for_mover(*eval) + 1) / 2
+            ));
+        } else if before.find_immediate_win_n(color.other(), self.connect) == Some(file) {
+            lines.push("It blocks an immediate win.".to_string());
+        }
+
+        if let Some((runner_up, eval, _)) = evaluations.iter().find(|(f, _, _)| *f != file) {
+            lines.push(format!(
+                "Runner-up: {} (eval {}).",
+                column(*runner_up),
+                format_score(for_mover(*eval))
+            ));
+        }
+
+        let expected = line
+            .iter()
+            .map(|&(color, file)| format!("{}{}", self.color_label(color), column(file)))
+            .collect::<Vec<_>>();
+        lines.push(format!("Expected line: {}", expected.join(" ")));
+
+        let parity = self.board.parity_summary(color, self.turn);
+        let mut key_columns = parity
+            .key_files
+            .iter()
+            .map(|&file| column(file))
+            .collect::<Vec<_>>();
+        key_columns.sort_unstable();
+        let key_columns = key_columns
+            .iter()
+            .map(|c| c.to_string())
+            .collect::<Vec<_>>();
+        match key_columns.len() {
+            0 => {}
+            1 => lines.push(format!(
+                "{} controls the key {} threat in column {}.",
+                self.color_label(color),
+                parity.favored,
+                key_columns[0]
+            )),
+            _ => lines.push(format!(
+                "{} controls key {} threats in columns {}.",
+                self.color_label(color),
+                parity.favored,
+                key_columns.join(", ")
+            )),
+        }
+
+        Some(lines.join("\n"))
+    }
+
+    /// The depth and time limit for the computer's next search. Adaptive play deepens up to the
+    /// end of the game within its budget; a clock, if running, caps the time either way.
+    fn search_limits(&self) -> (usize, Option<Duration>) {
+        // Budget for roughly twenty more moves so the clock lasts the game.
+        let clock = self
+            .clock
+            .as_ref()
+            .map(|clock| clock.remaining(self.player.other()) / 20);
+
+        match self.adaptive {
+            true => {
+                let depth = 42 - self.board.piece_count();
+                let budget = adaptive_budget(&self.board);
+                (
+                    self.depth_cap.map_or(depth, |cap| depth.min(cap)),
+                    Some(clock.map_or(budget, |clock| clock.min(budget))),
+                )
+            }
+            false => (self.engine_depth(), clock),
+        }
+    }
+
+    fn engine_depth(&self) -> usize {
+        self.depth_cap.map_or(self.depth, |cap| self.depth.min(cap))
+    }
+
+    /// Charges `color` for time spent on its move, returning the winner if its clock ran out.
+    fn charge_clock(&mut self, color: Color, elapsed: Duration) -> Option<Color> {
+        let clock = self.clock.as_mut()?;
+        if !clock.charge(color, elapsed) {
+            return None;
+        }
+
+        println!("{} ran out of time!", color);
+        Some(color.other())
+    }
+
+    fn read_color(&mut self) -> Color {
+        loop {
+            let buffer = self.read_input();
+            match buffer.as_str().trim_end() {
+                c if c.starts_with('y') || c.starts_with('Y') => return Color::Yellow,
+                c if c.starts_with('r') || c.starts_with('R') => return Color::Red,
                 _ => println!(
                     "Invalid color. Please type '{}' or '{}':",
                     "Y".yellow(),
@@ -101,11 +957,66 @@ impl Repl {
         }
     }
 
-    fn game_over(&self) {
-        println!("{}", self.board);
-        match self.turn {
-            Color::Red => println!("{}", "WINNER!!!".red()),
-            Color::Yellow => println!("{}", "WINNER!!!".yellow()),
+    /// Plays a whole game between two engines searching `red_depth` and `yellow_depth` plies
+    /// deep, from an empty board and apart from the game in progress, showing each move.
+    fn auto_play(&self, red_depth: usize, yellow_depth: usize) {
+        let mut red = MinimaxPlayer::new(red_depth);
+        let mut yellow = MinimaxPlayer::new(yellow_depth);
+        let (board, winner) = self.watch_game(&mut red, &mut yellow);
+        self.print_result(&board, winner);
+    }
+
+    /// Plays `red` against `yellow` from an empty board, Red first, rendering the board after
+    /// every move and pausing `auto_pause` between them. Returns the final board and winner.
+    fn watch_game(&self, red: &mut dyn Player, yellow: &mut dyn Player) -> (Board, Option<Color>) {
+        let mut board = Board::new();
+        let mut color = Color::Red;
+
+        while !board.legal_files().is_empty() {
+            let file = match color {
+                Color::Red => red.choose_move(&board, color),
+                Color::Yellow => yellow.choose_move(&board, color),
+            };
+            board.insert(file, color);
+            println!(
+                "{} plays {}.",
+                self.color_label(color),
+                self.visual_file(file) + 1
+            );
+            if board.last_move_wins(file) {
+                return (board, Some(color));
+            }
+
+            println!("{}", self.render_position(&board));
+            thread::sleep(self.auto_pause);
+            color = color.other();
+        }
+
+        (board, None)
+    }
+
+    fn end_game(&mut self, winner: Option<Color>) {
+        self.game_over(winner);
+        if !self.play_again() {
+            std::process::exit(0);
+        }
+    }
+
+    fn game_over(&self, winner: Option<Color>) {
+        self.print_result(&self.board, winner);
+    }
+
+    /// Shows `board` with the winning line highlighted, and who won.
+    fn print_result(&self, board: &Board, winner: Option<Color>) {
+        let mask = winner.map_or(0, |winner| board.winning_line_mask(winner));
+        println!(
+            "{}",
+            board.render_highlighting(mask, self.flipped, self.plain)
+        );
+        match winner {
+            Some(Color::Red) => println!("{}", "WINNER!!!".red()),
+            Some(Color::Yellow) => println!("{}", "WINNER!!!".yellow()),
+            None => println!("{}", "DRAW!!!".blue()),
         }
     }
 
@@ -116,33 +1027,893 @@ impl Repl {
 
         match buffer.as_str().trim_end() {
             "n" | "no" => return false,
-            _ => self.board = Board::new(),
+            _ => self.new_game(),
         }
 
         true
     }
 
-    fn read_input(&mut self) -> String {
-        let mut buffer = String::new();
+    fn think(&self) -> usize {
+        match self.interruptible {
+            true => println!("Computer is thinking... (press Enter to play now)"),
+            false => println!("Computer is thinking..."),
+        }
+
+        let start = Instant::now();
+        let stop = Arc::new(AtomicBool::new(false));
+        let (depth, time_limit) = self.search_limits();
+        let mut minimax = Minimax::new(&self.board, self.player.other(), depth)
+            .with_stop(stop.clone())
+            .with_connect(self.connect);
+        if let Some(limit) = time_limit {
+            minimax = minimax.with_time_limit(limit);
+        }
+
+        // Mcts rollouts only know Connect-4.
+        let engine = match self.connect {
+            4 => self.engine,
+            _ => Engine::Minimax,
+        };
+        let (board, color, seed) = (&self.board, self.player.other(), self.seed);
+        let (file, stats) = thread::scope(|s| {
+            let search = s.spawn(|| match engine {
+                Engine::Minimax => minimax.best_move_with_stats(),
+                Engine::Mcts => {
+                    let mut mcts = Mcts::new(board, color, 200_000).with_stop(stop.clone());
+                    if let Some(limit) = time_limit {
+                        mcts = mcts.with_time_limit(limit);
+                    }
+                    if let Some(seed) = seed {
+                        // A different stream each move, so rollouts don't repeat.
+                        mcts = mcts.with_seed(seed ^ board.piece_count() as u64);
+                    }
+                    let (file, playouts) = mcts.best_move_with_playouts();
+
+                    // Mcts doesn't evaluate positions, so log the static evaluation instead.
+                    let mut next = *board;
+                    next.insert(file, color);
+                    let stats = SearchStats {
+                        score: next.evaluate_with(&EvalWeights::default(), color.other()),
+                        depth: 0,
+                        nodes: playouts as u64,
+                    };
+                    (file, stats)
+                }
+            });
+            if self.interruptible {
+                self.request_line();
+            }
+            while !search.is_finished() {
+                let poll = Duration::from_millis(20);
+                match self.interruptible {
+                    true => match self.input.recv_timeout(poll) {
+                        Ok(_) => {
+                            self.waiting.set(false);
+                            stop.store(true, Ordering::Relaxed);
+                        }
+                        Err(RecvTimeoutError::Timeout) => {}
+                        Err(RecvTimeoutError::Disconnected) => thread::sleep(poll),
+                    },
+                    false => thread::sleep(poll),
+                }
+            }
+            search.join().expect("search thread panicked")
+        });
+
+        println!(
+            "Computer played {} after {:.2}s",
+            self.visual_file(file) + 1,
+            start.elapsed().as_secs_f32()
+        );
+        self.write_trace(file, &stats);
+
+        file
+    }
 
-        if self.reader.read_line(&mut buffer).is_err() {
-            panic!("info string \"failed to initialize stdin\" ");
+    /// Appends a computer move to the trace file, if enabled. File errors are reported but never
+    /// interrupt the game.
+    fn write_trace(&self, file: usize, stats: &SearchStats) {
+        let Some(path) = &self.trace else { return };
+
+        let line = format!(
+            "position={} color={} move={} eval={} depth={} nodes={}\n",
+            self.board.to_notation(),
+            match self.player.other() {
+                Color::Red => "red",
+                Color::Yellow => "yellow",
+            },
+            file + 1,
+            stats.score,
+            stats.depth,
+            stats.nodes
+        );
+        let result = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut trace| trace.write_all(line.as_bytes()));
+
+        if let Err(e) = result {
+            println!("Could not write trace to {}: {}", path.display(), e);
         }
+    }
 
-        buffer
+    /// Feedback on the player dropping in `file`, while coaching is on and the game is in the
+    /// opening.
+    fn coaching(&self, file: usize) -> Option<String> {
+        if !self.coach
+            || self.board.piece_count() >= OPENING_PLIES
+            || !self.board.legal_files().contains(&file)
+        {
+            return None;
+        }
+
+        let review = review_move(&self.board, self.turn, file, COACH_DEPTH, &self.book);
+        let feedback = match review.quality {
+            MoveQuality::Best if review.in_book => "Book move.".to_string(),
+            MoveQuality::Best => "Best move.".to_string(),
+            quality => format!(
+                "{} is {}: {} was best, by {}.",
+                self.visual_file(review.played) + 1,
+                quality,
+                self.visual_file(review.best) + 1,
+                review.loss
+            ),
+        };
+        Some(feedback)
+    }
+
+    /// Asks before the player drops in `file` while leaving an opponent win open.
+    fn confirm_move(&mut self, file: usize) -> bool {
+        // The threat check only knows Connect-4.
+        if self.connect != 4 {
+            return true;
+        }
+        let Some(threat) = self.board.unblocked_threat(file, self.turn) else {
+            return true;
+        };
+
+        println!(
+            "Opponent can win in column {} — are you sure? (y/n)",
+            self.visual_file(threat) + 1
+        );
+        let buffer = self.read_input();
+        matches!(buffer.trim_end(), "y" | "yes")
+    }
+
+    fn read_input(&mut self) -> String {
+        self.request_line();
+        // An empty string signals end of input, matching `BufRead::read_line`.
+        let line = self.input.recv().unwrap_or_default();
+        self.waiting.set(false);
+        line
+    }
+
+    /// Has the input thread read a line unless it is already reading one.
+    fn request_line(&self) {
+        if !self.waiting.replace(true) {
+            let _ = self.wants_line.send(());
+        }
     }
 
     fn insert_file(&mut self, file: usize) {
-        self.board.insert(file, self.turn);
-        if self.board.has_connect_4(self.turn) {
-            self.game_over();
-            if !self.play_again() {
-                std::process::exit(0);
+        let Ok(result) = self.board.try_move(file, self.turn) else {
+            return;
+        };
+        self.history.push((self.turn, file));
+        // The dead draw check only knows Connect-4, so other variants play until the board fills.
+        let drawn = match self.connect == 4 && !self.pop_out {
+            true => self.board.no_winning_chances(),
+            false => result.full,
+        };
+        let won = match self.connect {
+            4 => result.won,
+            n => self.board.has_connect_n(self.turn, n),
+        };
+        let repeated = self.record_position();
+        // The turn passes first, so a new game started from `end_game` keeps its own first turn.
+        let mover = self.turn;
+        self.turn = mover.other();
+        if won {
+            self.end_game(Some(mover));
+        } else if drawn || repeated {
+            self.end_game(None);
+        }
+    }
+
+    /// Takes back the player's last move along with the computer's reply, so it is the player's
+    /// turn in the position before it. In hotseat, takes back just the last move.
+    fn undo(&mut self) -> Result<(), &'static str> {
+        // Pops aren't in the history, so they can't be replayed backwards.
+        if self.pop_out {
+            return Err("Moves can't be taken back in Pop Out.");
+        }
+        let plies = match (self.hotseat, self.history.as_slice()) {
+            (true, [.., _]) => 1,
+            (false, [.., (first, _), (second, _)])
+                if *first == self.player && *second != self.player =>
+            {
+                2
+            }
+            _ => return Err("There is no move of yours to take back."),
+        };
+
+        for _ in 0..plies {
+            let (_, file) = self.history.pop().expect("counted above");
+            let _ = self.board.remove(file);
+        }
+        self.turn = match self.hotseat {
+            true => self.turn.other(),
+            false => self.player,
+        };
+
+        Ok(())
+    }
+
+    /// Pops the player's own piece from the bottom of `file`, in Pop Out. A pop that completes
+    /// lines for both sides wins for the player who popped.
+    fn pop_file(&mut self, file: usize) -> Result<(), &'static str> {
+        if !self.pop_out {
+            return Err("Popping is only allowed in Pop Out. Type 'popout on' to start a game.");
+        }
+        match self.board.piece_at(0, file) {
+            None => return Err("That column is empty."),
+            Some(color) if color != self.turn => return Err("You can only pop your own pieces."),
+            Some(_) => {}
+        }
+
+        let _ = self.board.pop_out(file);
+        let repeated = self.record_position();
+        let popper = self.turn;
+        self.turn = popper.other();
+        if self.board.has_connect_n(popper, self.connect) {
+            self.end_game(Some(popper));
+        } else if self.board.has_connect_n(popper.other(), self.connect) {
+            self.end_game(Some(popper.other()));
+        } else if repeated {
+            self.end_game(None);
+        }
+
+        Ok(())
+    }
+
+    /// Records the position after a move, returning true on its third occurrence. Positions
+    /// only repeat in Pop Out, since drops alone always add a piece.
+    fn record_position(&mut self) -> bool {
+        if !self.pop_out {
+            return false;
+        }
+
+        let position = (self.board.to_compact(), self.turn.other());
+        self.positions.push(position);
+        self.positions
+            .iter()
+            .filter(|&&seen| seen == position)
+            .count()
+            >= 3
+    }
+}
+
+/// Reads a line from the terminal with editing and history, or None at end of input. Ctrl-C
+/// exits, since the editor keeps the terminal from raising the signal itself.
+fn edit_line(editor: &mut DefaultEditor) -> Option<String> {
+    match editor.readline("") {
+        Ok(line) => {
+            let _ = editor.add_history_entry(line.as_str());
+            Some(line)
+        }
+        Err(ReadlineError::Interrupted) => std::process::exit(130),
+        Err(_) => None,
+    }
+}
+
+/// A score from one side's point of view, with forced wins as the moves they take: "+23", "#2",
+/// or "-#2" when losing.
+fn format_score(score: i32) -> String {
+    match score.abs() > MATE {
+        true => {
+            let moves = (INF - score.abs() + 1) / 2;
+            format!("{}#{}", if score < 0 { "-" } else { "" }, moves)
+        }
+        false => format!("{:+}", score),
+    }
+}
+
+/// How long an adaptive search may think, up to a second. Openings are cut short, since early
+/// moves matter less and branch the most; narrow positions get longer, as each extra second there
+/// buys more depth.
+fn adaptive_budget(board: &Board) -> Duration {
+    let base = match board.phase() {
+        Phase::Opening => 300,
+        Phase::Midgame => 600,
+        Phase::Endgame => 800,
+    };
+    let narrow = 7 - board.legal_files().len() as u64;
+
+    Duration::from_millis(base + 30 * narrow)
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn setup_loads_notation() {
+        let mut repl = Repl::with_reader(Cursor::new("r\n"));
+
+        repl.setup(" 7/7/7/7/2yyy2/2rrrr1").unwrap();
+
+        assert_eq!(
+            repl.board,
+            Board::from_notation("7/7/7/7/2yyy2/2rrrr1").unwrap()
+        );
+        assert_eq!(repl.turn, Color::Red);
+    }
+
+    #[test]
+    fn setup_loads_move_sequence() {
+        let mut repl = Repl::with_reader(Cursor::new("y\n"));
+
+        repl.setup(" 443").unwrap();
+
+        assert_eq!(
+            repl.board,
+            Board::from_notation("7/7/7/7/3y3/2rr3").unwrap()
+        );
+        assert_eq!(repl.turn, Color::Yellow);
+    }
+
+    #[test]
+    fn flipped_input_follows_visual_columns() {
+        let mut repl = Repl::with_reader(Cursor::new(""));
+        assert_eq!(repl.visual_file(0), 0);
+
+        repl.flipped = true;
+        assert_eq!(repl.visual_file(0), 6);
+        assert_eq!(repl.visual_file(4), 2);
+    }
+
+    #[test]
+    fn accepts_letter_columns() {
+        let mut repl = Repl::with_reader(Cursor::new(""));
+
+        assert_eq!(repl.parse_file(&repl.board, "c"), Ok(2));
+        assert_eq!(repl.parse_file(&repl.board, "C"), Ok(2));
+        assert_eq!(
+            repl.parse_file(&repl.board, "3"),
+            repl.parse_file(&repl.board, "c")
+        );
+        assert_eq!(
+            repl.parse_file(&repl.board, "h"),
+            Err("Bad file. Please enter 1-7 or A-G.")
+        );
+        assert_eq!(
+            repl.parse_file(&repl.board, "8"),
+            Err("Bad file. Please enter 1-7 or A-G.")
+        );
+        assert_eq!(repl.parse_file(&repl.board, "?"), Err("Unknown input."));
+
+        repl.board = Board::from_moves("333333").unwrap();
+        assert_eq!(
+            repl.parse_file(&repl.board, "c"),
+            Err("That column is full. Please choose another.")
+        );
+    }
+
+    #[test]
+    fn shows_eval_bar_for_side_to_move() {
+        let mut repl = Repl::with_reader(Cursor::new("")).with_plain();
+        repl.board = Board::new();
+        repl.turn = Color::Red;
+        assert_eq!(repl.eval_bar().as_deref(), Some("[-----|+++++] R +2"));
+
+        // Red wins in one.
+        repl.board = Board::from_notation("7/7/7/7/yyy4/rrr4").unwrap();
+        assert_eq!(repl.eval_bar().as_deref(), Some("[|++++++++++] R #1"));
+
+        repl.board = Board::from_notation("7/7/7/7/rrr4/yyyy3").unwrap();
+        repl.turn = Color::Yellow;
+        assert_eq!(repl.eval_bar(), None);
+    }
+
+    #[test]
+    fn plain_mode_renders_ascii() {
+        let mut repl = Repl::with_reader(Cursor::new("")).with_plain();
+        repl.board = Board::from_moves("4453").unwrap();
+
+        let rendered = repl.render_board();
+        assert_eq!(
+            rendered,
+            "  1  2  3  4  5  6  7   \n\
+             | .  .  .  .  .  .  . |\n\
+             | .  .  .  .  .  .  . |\n\
+             | .  .  .  .  .  .  . |\n\
+             | .  .  .  .  .  .  . |\n\
+             | .  .  .  Y  .  .  . |\n\
+             | .  .  Y  R  R  .  . |\n"
+        );
+        assert!(rendered.is_ascii());
+        assert!(!rendered.contains('\x1b'));
+        assert_eq!(repl.color_label(Color::Red), "R");
+    }
+
+    #[test]
+    fn formats_status_line() {
+        let mut repl = Repl::with_reader(Cursor::new(""));
+        repl.plain = true;
+        repl.board = Board::from_notation("7/7/7/7/1yyy3/1rrr3").unwrap();
+        repl.turn = Color::Red;
+
+        assert_eq!(
+            repl.status_line(),
+            "Move 7 | Turn: R | R threatens cols 1, 5 | Y threatens none"
+        );
+
+        repl.flipped = true;
+        repl.board = Board::from_notation("7/7/7/7/rr5/yyy4").unwrap();
+        repl.turn = Color::Yellow;
+        assert_eq!(
+            repl.status_line(),
+            "Move 6 | Turn: Y | R threatens none | Y threatens col 4"
+        );
+    }
+
+    #[test]
+    fn handicap_gives_human_first_move() {
+        for color in ["r", "y"].repeat(10) {
+            let mut repl =
+                Repl::with_reader(Cursor::new(format!("{}\n", color))).with_human_first();
+            repl.choose_color();
+            assert_eq!(repl.turn, repl.player);
+
+            repl.turn = repl.player.other();
+            repl.new_game();
+            assert_eq!(repl.turn, repl.player);
+        }
+
+        // A game the player wins on their move, followed by "y" for a new one.
+        let mut repl = Repl::with_reader(Cursor::new("y\n")).with_human_first();
+        repl.player = Color::Red;
+        repl.turn = Color::Red;
+        repl.board = Board::from_moves("171717").unwrap();
+        repl.insert_file(0);
+        assert_eq!(repl.board, Board::new());
+        assert_eq!(repl.turn, repl.player);
+
+        let mut repl = Repl::with_reader(Cursor::new("y\n"))
+            .with_human_first()
+            .with_pop_out();
+        repl.player = Color::Yellow;
+        repl.turn = Color::Yellow;
+        // Popping lets Red's piece fall into the bottom row, completing it.
+        repl.board = Board::from_notation("7/7/7/7/r1yy3/yrrr3").unwrap();
+        assert_eq!(repl.pop_file(0), Ok(()));
+        assert_eq!(repl.board, Board::new());
+        assert_eq!(repl.turn, repl.player);
+
+        let repl = Repl::with_reader(Cursor::new("")).with_depth_cap(4);
+        assert_eq!(repl.engine_depth(), 4);
+    }
+
+    #[test]
+    fn what_if_leaves_board_unchanged() {
+        let mut repl = Repl::with_reader(Cursor::new(""));
+        repl.depth = 4;
+        repl.player = Color::Yellow;
+        repl.board = Board::from_moves("445").unwrap();
+        let before = repl.board;
+
+        let (reply, _) = repl.what_if(0).unwrap();
+        assert_eq!(repl.board, before);
+        assert!(before.legal_files().contains(&reply));
+        // After Yellow ignores the bottom row, Red makes an open three.
+        assert!(matches!(reply, 2 | 5));
+
+        repl.player = Color::Red;
+        repl.board = Board::from_moves("414243").unwrap();
+        assert_eq!(repl.what_if(3), None);
+    }
+
+    #[test]
+    fn connect_3_game_ends_on_three() {
+        // A win ends the game and the "y" starts a new one, leaving an empty board.
+        let play = |connect: usize| {
+            let mut repl = Repl::with_reader(Cursor::new("y\n")).with_connect(connect);
+            repl.player = Color::Red;
+            repl.turn = Color::Red;
+            for file in [0, 6, 1, 6, 2] {
+                repl.insert_file(file);
             }
+            repl.board
+        };
+
+        assert_eq!(play(3), Board::new());
+        assert_eq!(play(4), Board::from_moves("17273").unwrap());
+
+        let mut repl = Repl::with_reader(Cursor::new("")).with_connect(3);
+        repl.depth = 2;
+        repl.player = Color::Yellow;
+        repl.turn = Color::Red;
+        repl.board = Board::from_moves("1727").unwrap();
+        assert_eq!(repl.think(), 2);
+    }
+
+    #[test]
+    fn computer_loses_on_time() {
+        let mut repl = Repl::with_reader(Cursor::new(""));
+        repl.player = Color::Red;
+        repl.clock = Some(Clock::new(Duration::from_millis(50)));
+
+        assert_eq!(
+            repl.charge_clock(Color::Yellow, Duration::from_millis(20)),
+            None
+        );
+        assert_eq!(
+            repl.charge_clock(Color::Yellow, Duration::from_millis(40)),
+            Some(Color::Red)
+        );
+        assert_eq!(
+            repl.clock.unwrap().remaining(Color::Red),
+            Duration::from_millis(50)
+        );
+    }
+
+    #[test]
+    fn clock_is_off_by_default() {
+        let mut repl = Repl::with_reader(Cursor::new(""));
+
+        assert_eq!(
+            repl.charge_clock(Color::Red, Duration::from_secs(600)),
+            None
+        );
+    }
+
+    #[test]
+    fn trace_logs_each_computer_move() {
+        let path = std::env::temp_dir().join(format!("connect-4-trace-{}.log", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut repl = Repl::with_reader(Cursor::new("")).with_trace(&path);
+        repl.depth = 2;
+        repl.player = Color::Yellow;
+        repl.turn = Color::Red;
+        for human_file in [0, 6] {
+            let file = repl.think();
+            repl.insert_file(file);
+            repl.insert_file(human_file);
         }
-        self.turn = match self.turn {
-            Color::Yellow => Color::Red,
-            Color::Red => Color::Yellow,
+
+        let trace = std::fs::read_to_string(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        let lines = trace.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("position=7/7/7/7/7/7 color=red move="));
+        assert!(lines.iter().all(|line| line.contains(" depth=2 ")));
+    }
+
+    #[test]
+    fn trace_ignores_file_errors() {
+        let repl = Repl::with_reader(Cursor::new("")).with_trace("/nonexistent/dir/trace.log");
+        let stats = SearchStats {
+            score: 0,
+            depth: 0,
+            nodes: 0,
         };
+
+        repl.write_trace(3, &stats);
+    }
+
+    #[test]
+    fn coach_reviews_opening_moves() {
+        let mut repl = Repl::with_reader(Cursor::new(""));
+        repl.turn = Color::Red;
+        assert_eq!(repl.coaching(3), None);
+
+        repl.coach = true;
+        assert_eq!(repl.coaching(3).as_deref(), Some("Book move."));
+
+        repl.board = Board::from_moves("445").unwrap();
+        repl.turn = Color::Yellow;
+        let feedback = repl.coaching(0).unwrap();
+        assert!(feedback.starts_with("1 is a blunder"), "{}", feedback);
+    }
+
+    #[test]
+    fn confirms_moves_that_ignore_a_threat() {
+        let mut repl = Repl::with_reader(Cursor::new("n\ny\n"));
+        repl.board = Board::from_moves("15253").unwrap();
+        repl.turn = Color::Yellow;
+
+        assert!(repl.confirm_move(3));
+        assert!(!repl.confirm_move(6));
+        assert!(repl.confirm_move(6));
+    }
+
+    #[test]
+    fn pop_out_draws_on_threefold_repetition() {
+        let mut repl = Repl::with_reader(Cursor::new("y\n"));
+        repl.pop_out = true;
+        repl.board = Board::from_moves("444").unwrap();
+        repl.turn = Color::Yellow;
+
+        assert_eq!(repl.pop_file(3), Err("You can only pop your own pieces."));
+        assert_eq!(repl.pop_file(0), Err("That column is empty."));
+
+        // Both sides drop and then pop the same pieces, so every position comes around again.
+        for _ in 0..2 {
+            repl.insert_file(0);
+            repl.insert_file(1);
+            repl.pop_file(0).unwrap();
+            repl.pop_file(1).unwrap();
+        }
+        assert_eq!(repl.board, Board::from_moves("444").unwrap());
+
+        // The third time is a draw, and a new game starts.
+        repl.insert_file(0);
+        assert_eq!(repl.board, Board::new());
+        assert!(repl.positions.is_empty());
+    }
+
+    #[test]
+    fn pops_only_in_pop_out() {
+        let mut repl = Repl::with_reader(Cursor::new(""));
+        repl.board = Board::from_moves("4").unwrap();
+        repl.turn = Color::Red;
+
+        assert!(repl.pop_file(3).is_err());
+        assert_eq!(repl.board, Board::from_moves("4").unwrap());
+    }
+
+    #[test]
+    fn loads_share_codes() {
+        let board = Board::from_moves("4455").unwrap();
+        let mut repl = Repl::with_reader(Cursor::new("r\n"));
+
+        repl.load(&share::encode(&board)).unwrap();
+
+        assert_eq!(repl.board, board);
+        assert_eq!(repl.turn, Color::Red);
+        assert!(repl.load("not-a-code").is_err());
+    }
+
+    #[test]
+    fn piped_input_is_read_line_by_line() {
+        let mut repl = Repl::with_reader(Cursor::new("setup 44\n\nr"));
+
+        assert_eq!(repl.read_input(), "setup 44\n");
+        assert_eq!(repl.read_input(), "\n");
+        assert_eq!(repl.read_input(), "r\n");
+        assert_eq!(repl.read_input(), "");
+    }
+
+    #[test]
+    fn piped_moves_survive_the_computer_turn() {
+        // Without a terminal, the lines queued behind a move are moves, not keypresses that
+        // cut the computer's search short.
+        let mut repl = Repl::with_input(None, Cursor::new("4\n3\nq\n"));
+        repl.depth = 4;
+        repl.player = Color::Red;
+        repl.turn = Color::Red;
+
+        assert!(repl.step());
+        assert!(repl.step());
+        assert!(repl.step());
+        let red = repl
+            .history
+            .iter()
+            .filter(|&&(color, _)| color == Color::Red);
+        assert_eq!(red.map(|&(_, file)| file).collect::<Vec<_>>(), [3, 2]);
+        assert!(repl.step());
+        assert!(!repl.step());
+    }
+
+    #[test]
+    fn reads_a_line_only_when_one_is_wanted() {
+        let (sent, count) = mpsc::channel();
+        let mut repl = Repl::with_lines(["4", "5"].into_iter().map(move |line| {
+            let _ = sent.send(());
+            line.to_string()
+        }));
+
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(count.try_iter().count(), 0);
+        assert_eq!(repl.read_input(), "4\n");
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(count.try_iter().count(), 1);
+    }
+
+    #[test]
+    fn setup_rejects_malformed_input() {
+        let mut repl = Repl::with_reader(Cursor::new(""));
+        let before = repl.board;
+
+        assert!(repl.setup(" 7/7/x").is_err());
+        assert!(repl.setup(" 8/7/7/7/7/7/7").is_err());
+        assert!(repl.setup(" 1111111").is_err());
+        assert_eq!(repl.board, before);
+    }
+
+    #[test]
+    fn analyze_loads_a_code_without_touching_the_game() {
+        let position = Board::from_notation("7/7/7/7/yyy4/rrr4").unwrap();
+        let code = share::encode(&position);
+        let mut repl = Repl::with_reader(Cursor::new("r\n"));
+        repl.board = Board::from_moves("44").unwrap();
+        let game = repl.board;
+
+        let mut analysis = repl.start_analysis(&code).unwrap();
+        assert_eq!(analysis.board, position);
+        assert_eq!(analysis.turn, Color::Red);
+
+        // Connecting only reports the line; further moves are refused until it is taken back.
+        assert!(repl.analysis_command(&mut analysis, "4"));
+        assert!(repl.analysis_command(&mut analysis, "5"));
+        assert_eq!(analysis.moves, vec![3]);
+        assert_eq!(analysis.turn, Color::Yellow);
+        assert!(analysis.board.has_connect_n(Color::Red, 4));
+
+        assert!(repl.analysis_command(&mut analysis, "undo"));
+        assert!(repl.analysis_command(&mut analysis, "7"));
+        assert_eq!(analysis.moves, vec![6]);
+        assert_eq!(analysis.turn, Color::Yellow);
+
+        assert!(!repl.analysis_command(&mut analysis, "done"));
+        assert_eq!(repl.board, game);
+
+        let compact = position.to_compact().to_string();
+        let mut repl = Repl::with_reader(Cursor::new("y\n"));
+        assert_eq!(repl.start_analysis(&compact).unwrap().board, position);
+        assert!(repl.start_analysis("not-a-code").is_err());
+    }
+
+    #[test]
+    fn analysis_checks_moves_against_its_own_board() {
+        let mut repl = Repl::with_reader(Cursor::new(""));
+        repl.board = Board::from_moves("111111").unwrap();
+        let mut analysis = Analysis {
+            board: Board::from_moves("777777").unwrap(),
+            turn: Color::Red,
+            moves: Vec::new(),
+        };
+
+        // The first column is full only in the game, the last only in the analysis.
+        assert!(repl.analysis_command(&mut analysis, "7"));
+        assert!(repl.analysis_command(&mut analysis, "whatif 7"));
+        assert!(analysis.moves.is_empty());
+        assert_eq!(analysis.board, Board::from_moves("777777").unwrap());
+
+        assert!(repl.analysis_command(&mut analysis, "1"));
+        assert_eq!(analysis.moves, vec![0]);
+        assert_eq!(analysis.turn, Color::Yellow);
+    }
+
+    #[test]
+    fn hotseat_plays_both_colors_from_input() {
+        // Yellow confirms leaving file 4 open, Red wins up it, and the last "y" starts a new game.
+        let input = "4\n3\n4\n3\n4\n3\ny\n4\ny\nq\n";
+        let mut repl = Repl::with_reader(Cursor::new(input)).with_hotseat();
+        repl.turn = Color::Red;
+
+        for _ in 0..6 {
+            assert!(repl.step());
+        }
+        assert_eq!(repl.board, Board::from_moves("434343").unwrap());
+        assert_eq!(repl.turn, Color::Red);
+
+        assert!(repl.step());
+        assert_eq!(repl.board, Board::new());
+        assert!(!repl.step());
+    }
+
+    #[test]
+    fn adaptive_search_gives_narrow_positions_more_time() {
+        let limits = |board: Board| {
+            let mut repl = Repl::with_reader(Cursor::new("")).with_adaptive();
+            repl.board = board;
+            repl.search_limits()
+        };
+
+        // The open board gets the opening budget; the late position, with the endgame budget and
+        // 30ms for each of its four full files, may search all the way to the end.
+        let narrow = Board::from_notation("y1r2yy/yrr2yr/rry2rr/ryy2ry/ryr1ryy/yryyryr").unwrap();
+        assert_eq!(limits(Board::new()), (42, Some(Duration::from_millis(300))));
+        assert_eq!(
+            limits(narrow),
+            (42 - narrow.piece_count(), Some(Duration::from_millis(920)))
+        );
+
+        let mut repl = Repl::with_reader(Cursor::new("")).with_adaptive();
+        repl.board = narrow;
+        repl.depth_cap = Some(6);
+        repl.clock = Some(Clock::new(Duration::from_secs(10)));
+        assert_eq!(repl.search_limits(), (6, Some(Duration::from_millis(500))));
+    }
+
+    #[test]
+    fn undo_takes_back_the_computer_reply_too() {
+        let mut repl = Repl::with_reader(Cursor::new(""));
+        repl.player = Color::Red;
+        repl.turn = Color::Red;
+        assert!(repl.undo().is_err());
+
+        repl.insert_file(3);
+        repl.insert_file(2);
+        repl.insert_file(4);
+        repl.insert_file(4);
+        assert!(repl.undo().is_ok());
+        assert_eq!(repl.board, Board::from_moves("43").unwrap());
+        assert_eq!(repl.history, vec![(Color::Red, 3), (Color::Yellow, 2)]);
+        assert_eq!(repl.turn, Color::Red);
+
+        assert!(repl.undo().is_ok());
+        assert_eq!(repl.board, Board::new());
+        assert!(repl.undo().is_err());
+
+        // Only the computer has moved, so there is nothing of the player's to take back.
+        repl.turn = Color::Yellow;
+        repl.insert_file(3);
+        assert!(repl.undo().is_err());
+        assert_eq!(repl.board.piece_count(), 1);
+    }
+
+    #[test]
+    fn explains_the_computer_move() {
+        let mut repl = Repl::with_reader(Cursor::new("")).with_plain();
+        repl.player = Color::Yellow;
+        repl.turn = Color::Red;
+        assert_eq!(repl.explain(), None);
+
+        // Red, the computer, can win in two moves from here.
+        repl.board = Board::from_notation("7/4r2/4y2/3ry1y/r2yrry/yy1ryrr").unwrap();
+        let file = Minimax::new(&repl.board, Color::Red, 4).best_move();
+        repl.insert_file(file);
+
+        let explanation = repl.explain().unwrap();
+        let lines = explanation.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], format!("R played {} (eval #2).", file + 1));
+        assert_eq!(lines[1], "It forces a win in 2.");
+        assert!(lines[2].starts_with("Runner-up: "));
+        // Yellow has no defence, so the line ends with Red's second move.
+        assert_eq!(file, 5);
+        assert_eq!(lines[3], "Expected line: R6 Y1 R6");
+
+        // Three in a row on the bottom row leaves Red, who moved first, an odd threat.
+        repl.board = Board::from_notation("7/7/7/7/yy5/rr5").unwrap();
+        repl.turn = Color::Red;
+        repl.insert_file(2);
+        let explanation = repl.explain().unwrap();
+        assert_eq!(
+            explanation.lines().last(),
+            Some("R controls the key odd threat in column 4.")
+        );
+    }
+
+    #[test]
+    fn auto_play_finishes_an_engine_game() {
+        let mut repl = Repl::with_reader(Cursor::new("")).with_plain();
+        repl.auto_pause = Duration::ZERO;
+        repl.board = Board::from_moves("44").unwrap();
+
+        let mut red = MinimaxPlayer::new(4);
+        let mut yellow = MinimaxPlayer::new(2);
+        let (board, winner) = repl.watch_game(&mut red, &mut yellow);
+        assert!(board.validate().is_ok());
+        match winner {
+            Some(color) => assert_ne!(board.winning_line_mask(color), 0),
+            None => assert!(board.legal_files().is_empty()),
+        }
+        // The game in progress is left alone.
+        assert_eq!(repl.board, Board::from_moves("44").unwrap());
+
+        // Scripted, the command plays a game and hands the turn back.
+        let mut repl = Repl::with_reader(Cursor::new("auto 2 1\nq\n")).with_plain();
+        repl.auto_pause = Duration::ZERO;
+        repl.player = Color::Yellow;
+        repl.turn = Color::Yellow;
+        assert!(repl.step());
+        assert_eq!(repl.board, Board::new());
+        assert!(!repl.step());
     }
 }
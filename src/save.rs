@@ -0,0 +1,162 @@
+use std::{
+    fs,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::bail;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::board::{Board, Color};
+
+/// Everything needed to resume a game from the Repl, stored as JSON:
+///
+/// {"board":"7/7/7/7/3y3/3r3","turn":"red","player":"yellow","history":[["red",3],["yellow",3]],
+///  "depth":12,"timestamp":1700000000}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedGame {
+    #[serde(serialize_with = "to_notation", deserialize_with = "from_notation")]
+    pub board: Board,
+    pub turn: Color,
+    pub player: Color,
+    pub history: Vec<(Color, usize)>,
+    pub depth: usize,
+    /// Seconds since the unix epoch
+    pub timestamp: u64,
+}
+
+impl SavedGame {
+    pub fn new(
+        board: Board,
+        turn: Color,
+        player: Color,
+        history: Vec<(Color, usize)>,
+        depth: usize,
+    ) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+
+        SavedGame {
+            board,
+            turn,
+            player,
+            history,
+            depth,
+            timestamp,
+        }
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), anyhow::Error> {
+        fs::write(path, self.to_json())?;
+        Ok(())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, anyhow::Error> {
+        SavedGame::from_json(&fs::read_to_string(path)?)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("saved games always serialize")
+    }
+
+    /// Parses a saved game, rejecting one whose history doesn't end in its board, since undo
+    /// after resuming would take back the wrong pieces.
+    pub fn from_json(json: &str) -> Result<Self, anyhow::Error> {
+        let game: SavedGame = serde_json::from_str(json)?;
+        game.check_history()?;
+        Ok(game)
+    }
+
+    /// Takes the history back from the board, checking each move's piece is on top of its file
+    /// and that the moves alternate up to the side to move. Whatever remains is the position the
+    /// history started from, which need not be empty after `setup`.
+    fn check_history(&self) -> Result<(), anyhow::Error> {
+        let mut board = self.board;
+        let mut next = self.turn;
+        for &(color, file) in self.history.iter().rev() {
+            if color == next {
+                bail!("saved history doesn't alternate up to the side to move");
+            }
+            if board.remove(file).ok() != Some(color) {
+                bail!("saved history doesn't match the saved board");
+            }
+            next = color;
+        }
+
+        Ok(())
+    }
+}
+
+fn to_notation<S: Serializer>(board: &Board, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&board.to_notation())
+}
+
+fn from_notation<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Board, D::Error> {
+    let notation = String::deserialize(deserializer)?;
+    Board::from_notation_strict(&notation).map_err(serde::de::Error::custom)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn round_trips_saved_game() {
+        let history = vec![
+            (Color::Red, 3),
+            (Color::Yellow, 3),
+            (Color::Red, 2),
+            (Color::Yellow, 4),
+        ];
+        let mut board = Board::new();
+        for (color, file) in &history {
+            board.insert(*file, *color);
+        }
+        let game = SavedGame::new(board, Color::Red, Color::Yellow, history, 8);
+
+        let json = game.to_json();
+        assert_eq!(SavedGame::from_json(&json).unwrap(), game);
+    }
+
+    #[test]
+    fn rejects_malformed_saved_game() {
+        assert!(SavedGame::from_json("").is_err());
+        assert!(SavedGame::from_json("{\"board\":\"7/7/7/7/7/7\"}").is_err());
+        assert!(SavedGame::from_json("[1,2]").is_err());
+    }
+
+    #[test]
+    fn rejects_an_illegal_saved_board() {
+        // A yellow piece floating above an empty file.
+        let json = "{\"board\":\"7/7/7/7/3y3/7\",\"turn\":\"red\",\"player\":\"yellow\",\
+                    \"history\":[],\"depth\":12,\"timestamp\":0}";
+
+        let error = SavedGame::from_json(json).unwrap_err().to_string();
+        assert!(
+            error.contains("pieces float above empty cells"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn rejects_history_that_doesnt_match_the_board() {
+        let json = |history: &str| {
+            format!(
+                "{{\"board\":\"7/7/7/7/3y3/3r3\",\"turn\":\"red\",\"player\":\"yellow\",\
+                 \"history\":{},\"depth\":12,\"timestamp\":0}}",
+                history
+            )
+        };
+
+        assert!(SavedGame::from_json(&json("[[\"red\",3],[\"yellow\",3]]")).is_ok());
+        // A set-up position keeps only the moves since.
+        assert!(SavedGame::from_json(&json("[[\"yellow\",3]]")).is_ok());
+        assert!(SavedGame::from_json(&json("[[\"red\",3],[\"yellow\",2]]")).is_err());
+        assert!(SavedGame::from_json(&json("[[\"yellow\",3],[\"red\",3]]")).is_err());
+        assert!(SavedGame::from_json(&json("[[\"red\",3]]")).is_err());
+        assert!(SavedGame::from_json(&json("[[\"red\",3],[\"yellow\",3],[\"red\",3]]")).is_err());
+    }
+}
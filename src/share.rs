@@ -0,0 +1,80 @@
+use anyhow::{anyhow, bail};
+
+use crate::board::Board;
+
+/// The URL-safe base64 alphabet from RFC 4648.
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// `Board::to_compact` fits in 49 bits, so 7 bytes cover every position.
+const CODE_BYTES: usize = 7;
+
+/// A short code for pasting a position into chats or issues: the compact encoding as 7
+/// big-endian bytes in unpadded URL-safe base64, always 10 characters.
+pub fn encode(board: &Board) -> String {
+    let bytes = &board.to_compact().to_be_bytes()[8 - CODE_BYTES..];
+
+    let mut code = String::new();
+    for chunk in bytes.chunks(3) {
+        let mut group = [0; 3];
+        group[..chunk.len()].copy_from_slice(chunk);
+        let bits = u32::from_be_bytes([0, group[0], group[1], group[2]]);
+
+        // n bytes carry 8n bits, which take n + 1 base64 digits.
+        for i in 0..=chunk.len() {
+            code.push(ALPHABET[(bits >> (18 - 6 * i) & 0x3F) as usize] as char);
+        }
+    }
+
+    code
+}
+
+/// The inverse of `encode`.
+pub fn decode(code: &str) -> Result<Board, anyhow::Error> {
+    let code = code.trim();
+    if code.len() != 10 {
+        bail!(
+            "share codes are 10 characters, got {}: {}",
+            code.len(),
+            code
+        );
+    }
+
+    let mut compact = 0u64;
+    for c in code.bytes() {
+        let digit = ALPHABET
+            .iter()
+            .position(|&a| a == c)
+            .ok_or(anyhow!("invalid character in share code: {}", c as char))?;
+        compact = compact << 6 | digit as u64;
+    }
+
+    // 10 digits carry 60 bits, the last 4 of which are padding.
+    if compact & 0xF != 0 {
+        bail!("invalid share code: {}", code);
+    }
+    Ok(Board::from_compact(compact >> 4)?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn round_trips_share_codes() {
+        for moves in ["", "4", "4455", "123456712345671234567", "444444333333"] {
+            let board = Board::from_moves(moves).unwrap();
+            let code = encode(&board);
+
+            assert_eq!(code.len(), 10);
+            assert!(
+                code.bytes()
+                    .all(|c| c.is_ascii_alphanumeric() || c == b'-' || c == b'_')
+            );
+            assert_eq!(decode(&code).unwrap(), board);
+        }
+
+        assert!(decode("AAAAAAAAAA").is_err());
+        assert!(decode("short").is_err());
+        assert!(decode("!!!!!!!!!!").is_err());
+    }
+}
@@ -0,0 +1,216 @@
+use std::fmt;
+
+use rand::{SeedableRng, rngs::StdRng};
+
+use crate::{
+    board::Color,
+    player::{Player, play_game},
+};
+
+/// Elo differences are capped here so a perfect or winless score stays finite.
+const MAX_ELO: f64 = 800.0;
+
+pub struct Entrant {
+    pub name: String,
+    pub player: Box<dyn Player>,
+}
+
+impl Entrant {
+    pub fn new(name: impl Into<String>, player: impl Player + 'static) -> Self {
+        Entrant {
+            name: name.into(),
+            player: Box::new(player),
+        }
+    }
+}
+
+/// A seeded round-robin between engine configurations, for offline strength measurement.
+pub struct Tournament {
+    entrants: Vec<Entrant>,
+    /// Games per pairing, alternating which entrant plays Red.
+    games: usize,
+    /// Random plies at the start of each game, so repeated pairings differ.
+    random_plies: usize,
+    seed: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    pub name: String,
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+/// Tournament results, best score first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Standings {
+    pub records: Vec<Record>,
+}
+
+impl Tournament {
+    pub fn new(entrants: Vec<Entrant>, games: usize) -> Self {
+        Tournament {
+            entrants,
+            games,
+            random_plies: 2,
+            seed: 0,
+        }
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn with_random_plies(mut self, random_plies: usize) -> Self {
+        self.random_plies = random_plies;
+        self
+    }
+
+    pub fn run(mut self) -> Standings {
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let mut records = self
+            .entrants
+            .iter()
+            .map(|entrant| Record {
+                name: entrant.name.clone(),
+                wins: 0,
+                draws: 0,
+                losses: 0,
+            })
+            .collect::<Vec<_>>();
+
+        for a in 0..self.entrants.len() {
+            for b in a + 1..self.entrants.len() {
+                for game in 0..self.games {
+                    let (first, second) = self.entrants.split_at_mut(b);
+                    let (player_a, player_b) = (&mut first[a].player, &mut second[0].player);
+
+                    let (red, yellow, a_color) = match game % 2 {
+                        0 => (player_a, player_b, Color::Red),
+                        _ => (player_b, player_a, Color::Yellow),
+                    };
+
+                    match play_game(red.as_mut(), yellow.as_mut(), self.random_plies, &mut rng) {
+                        Some(winner) if winner == a_color => {
+                            records[a].wins += 1;
+                            records[b].losses += 1;
+                        }
+                        Some(_) => {
+                            records[a].losses += 1;
+                            records[b].wins += 1;
+                        }
+                        None => {
+                            records[a].draws += 1;
+                            records[b].draws += 1;
+                        }
+                    }
+                }
+            }
+        }
+
+        records.sort_by(|a, b| b.score().total_cmp(&a.score()));
+        Standings { records }
+    }
+}
+
+impl Record {
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    /// Wins plus half of the draws.
+    pub fn score(&self) -> f64 {
+        self.wins as f64 + self.draws as f64 / 2.0
+    }
+
+    pub fn win_rate(&self) -> f64 {
+        match self.games() {
+            0 => 0.0,
+            games => self.score() / games as f64,
+        }
+    }
+
+    /// A rough Elo rating relative to the average opponent, from the logistic Elo curve.
+    pub fn elo(&self) -> f64 {
+        let rate = self.win_rate();
+        match rate {
+            r if r <= 0.0 => -MAX_ELO,
+            r if r >= 1.0 => MAX_ELO,
+            r => (400.0 * (r / (1.0 - r)).log10()).clamp(-MAX_ELO, MAX_ELO),
+        }
+    }
+}
+
+impl fmt::Display for Standings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self
+            .records
+            .iter()
+            .map(|record| record.name.len())
+            .max()
+            .unwrap_or_default()
+            .max("Player".len());
+
+        writeln!(
+            f,
+            "{:<width$}  {:>5}  {:>4}  {:>5}  {:>6}  {:>7}  {:>5}",
+            "Player", "Games", "Wins", "Draws", "Losses", "Win %", "Elo"
+        )?;
+        for record in &self.records {
+            writeln!(
+                f,
+                "{:<width$}  {:>5}  {:>4}  {:>5}  {:>6}  {:>6.1}%  {:>+5.0}",
+                record.name,
+                record.games(),
+                record.wins,
+                record.draws,
+                record.losses,
+                record.win_rate() * 100.0,
+                record.elo()
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::player::{MinimaxPlayer, RandomPlayer};
+
+    fn tiny_tournament() -> Tournament {
+        Tournament::new(
+            vec![
+                Entrant::new("minimax", MinimaxPlayer::new(2)),
+                Entrant::new("random", RandomPlayer::new(359)),
+            ],
+            2,
+        )
+        .with_seed(359)
+    }
+
+    #[test]
+    fn tiny_tournament_is_consistent() {
+        let standings = tiny_tournament().run();
+
+        assert_eq!(standings.records.len(), 2);
+        for record in &standings.records {
+            assert_eq!(record.games(), 2);
+        }
+
+        let (first, second) = (&standings.records[0], &standings.records[1]);
+        assert_eq!(first.wins, second.losses);
+        assert_eq!(first.draws, second.draws);
+        assert!(first.score() >= second.score());
+        assert!((first.elo() + second.elo()).abs() < 1e-9);
+
+        let table = standings.to_string();
+        assert_eq!(table.lines().count(), 3);
+        assert!(table.contains("minimax") && table.contains("random"));
+
+        assert_eq!(tiny_tournament().run(), standings);
+    }
+}
@@ -0,0 +1,121 @@
+use rand::{Rng, SeedableRng, rngs::StdRng};
+
+use crate::{
+    board::{Color, EvalWeights},
+    player::{MinimaxPlayer, play_game},
+};
+
+/// Tunes `EvalWeights` with a (1+1) evolution strategy: each iteration mutates the best weights
+/// found so far and keeps the mutation if it wins a self-play match against them.
+pub struct Tuner {
+    /// Games per match, alternating which side plays Red.
+    pub games: usize,
+    /// The largest change to any one weight per mutation.
+    pub mutation: i32,
+    pub iterations: usize,
+    pub depth: usize,
+    /// Random plies at the start of each game, so games between the same weights differ.
+    pub random_plies: usize,
+    pub seed: u64,
+}
+
+impl Default for Tuner {
+    fn default() -> Self {
+        Tuner {
+            games: 10,
+            mutation: 4,
+            iterations: 50,
+            depth: 4,
+            random_plies: 2,
+            seed: 0,
+        }
+    }
+}
+
+impl Tuner {
+    pub fn run(&self, start: EvalWeights) -> EvalWeights {
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let mut best = start;
+
+        for _ in 0..self.iterations {
+            let candidate = self.mutate(&best, &mut rng);
+            if self.match_score(&candidate, &best, &mut rng) > self.games as f32 / 2.0 {
+                best = candidate;
+            }
+        }
+
+        best
+    }
+
+    fn mutate(&self, weights: &EvalWeights, rng: &mut impl Rng) -> EvalWeights {
+        let mut step = || rng.gen_range(-self.mutation..=self.mutation);
+
+        EvalWeights {
+            potential: (weights.potential + step()).max(0),
+            connect_4: (weights.connect_4 + step()).max(1),
+            open_three: (weights.open_three + step()).max(0),
+            parity: (weights.parity + step()).max(0),
+            center: (weights.center + step()).max(0),
+            reach: weights.reach,
+        }
+    }
+
+    /// Points scored by `candidate` against `incumbent`, counting draws as half a point.
+    fn match_score(
+        &self,
+        candidate: &EvalWeights,
+        incumbent: &EvalWeights,
+        rng: &mut impl Rng,
+    ) -> f32 {
+        (0..self.games)
+            .map(|game| {
+                let candidate_color = match game % 2 {
+                    0 => Color::Red,
+                    _ => Color::Yellow,
+                };
+                let mut candidate = MinimaxPlayer {
+                    depth: self.depth,
+                    weights: *candidate,
+                };
+                let mut incumbent = MinimaxPlayer {
+                    depth: self.depth,
+                    weights: *incumbent,
+                };
+                let (red, yellow) = match candidate_color {
+                    Color::Red => (&mut candidate, &mut incumbent),
+                    Color::Yellow => (&mut incumbent, &mut candidate),
+                };
+
+                match play_game(red, yellow, self.random_plies, rng) {
+                    Some(winner) if winner == candidate_color => 1.0,
+                    Some(_) => 0.0,
+                    None => 0.5,
+                }
+            })
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn tuner_returns_valid_weights() {
+        let tuner = Tuner {
+            games: 2,
+            mutation: 3,
+            iterations: 3,
+            depth: 2,
+            random_plies: 2,
+            seed: 357,
+        };
+
+        let weights = tuner.run(EvalWeights::default());
+
+        assert!(weights.potential >= 0);
+        assert!(weights.connect_4 >= 1);
+        assert!(weights.open_three >= 0);
+        assert!(weights.parity >= 0);
+    }
+}